@@ -0,0 +1,384 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use crate::employees::{sum_of_hours, Employee};
+use crate::excel::records::{read_records, Record};
+use crate::excel::ExcelError;
+use crate::validation::Issue;
+
+#[derive(Debug, Clone, Default)]
+struct SegmentValue {
+    id: String,
+    desc: String,
+}
+
+/// The GL segment values (departments, locations, ...) timecards are allowed to use.
+///
+/// Each row has a `GLSEG` column (1 to 3, the detail column the value goes in), the Sage
+/// `SEGMENTID` it belongs to, the segment `VALUE` and an optional `DESC`.
+#[derive(Debug, Clone, Default)]
+pub struct SegmentList {
+    values: [HashMap<String, SegmentValue>; 3],
+}
+
+impl SegmentList {
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut list = Self::default();
+
+        for record in records.iter() {
+            let position = record.get("GLSEG").unwrap_or_default();
+            let n = match position.parse::<usize>() {
+                Ok(n) if (1..=3).contains(&n) => n - 1,
+                _ => {
+                    return Err(ExcelError::Msg(format!(
+                        "GL segment list has `GLSEG` of `{}`, expected 1, 2 or 3",
+                        position
+                    )))
+                }
+            };
+            let value = match record.get("VALUE") {
+                Some(value) => value.to_string(),
+                None => continue,
+            };
+
+            list.values[n].insert(
+                value,
+                SegmentValue {
+                    id: record.get_string("SEGMENTID"),
+                    desc: record.get_string("DESC"),
+                },
+            );
+        }
+
+        Ok(list)
+    }
+
+    /// Checks every segment value used by a shift against the list, filling in the
+    /// segment id and description for the ones that are known.
+    pub fn apply(&self, employees: &mut [Employee]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for employee in employees.iter_mut() {
+            let mut unknown = HashSet::new();
+
+            for shift in employee.hours.iter_mut() {
+                for (n, segment) in shift.segments.iter_mut().enumerate() {
+                    if segment.value.is_empty() {
+                        continue;
+                    }
+
+                    match self.values[n].get(&segment.value) {
+                        Some(known) => {
+                            segment.id = known.id.clone();
+                            segment.desc = known.desc.clone();
+                        }
+                        None => {
+                            unknown.insert((n, segment.value.clone()));
+                        }
+                    }
+                }
+            }
+
+            let mut unknown: Vec<(usize, String)> = unknown.into_iter().collect();
+            unknown.sort();
+            for (n, value) in unknown {
+                issues.push(Issue::error(
+                    Some(&employee.id),
+                    format!(
+                        "GL segment {} value `{}` is not in the segment list",
+                        n + 1,
+                        value
+                    ),
+                ));
+            }
+        }
+
+        issues
+    }
+}
+
+/// Account numbers are compared without their segment separators, so `6000-100` from a
+/// formatted export matches `6000100` typed into a timecard.
+fn normalize_account(account: &str) -> String {
+    account
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// The GL accounts exported from Sage GL, keyed by `ACCTID` (or `ACCTFMTTD`).
+///
+/// Accounts with an `ACTIVESW` of 0 are known but rejected, as Sage won't post to them.
+#[derive(Debug, Clone, Default)]
+pub struct ChartOfAccounts {
+    accounts: HashMap<String, bool>,
+}
+
+impl ChartOfAccounts {
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut chart = Self::default();
+
+        for record in records.iter() {
+            let account = match record.get("ACCTID").or_else(|| record.get("ACCTFMTTD")) {
+                Some(account) => account,
+                None => continue,
+            };
+            let active = record.get("ACTIVESW") != Some("0");
+            chart.accounts.insert(normalize_account(account), active);
+        }
+
+        if chart.accounts.is_empty() {
+            return Err(ExcelError::Msg(
+                "chart of accounts has no `ACCTID` or `ACCTFMTTD` column".to_string(),
+            ));
+        }
+
+        Ok(chart)
+    }
+
+    /// Checks every account an employee with hours will post to, reporting each unknown or
+    /// inactive account once along with the employees that use it.
+    pub fn validate(&self, employees: &[Employee]) -> Vec<Issue> {
+        let mut unknown: BTreeMap<(String, &str), BTreeSet<String>> = BTreeMap::new();
+
+        for employee in employees.iter() {
+            if sum_of_hours(employee.hours.clone()) <= 0.0 {
+                continue;
+            }
+
+            for (column, account) in [
+                ("EXPACCT", employee.exp_account.as_str()),
+                ("OTACCT", employee.overtime_account()),
+                ("LIABACCT", employee.liab_account.as_str()),
+                ("SHIFTACCT", employee.shift_account.as_str()),
+            ] {
+                if account.is_empty()
+                    || self.accounts.get(&normalize_account(account)) == Some(&true)
+                {
+                    continue;
+                }
+                unknown
+                    .entry((account.to_string(), column))
+                    .or_default()
+                    .insert(employee.id.clone());
+            }
+        }
+
+        unknown
+            .into_iter()
+            .map(|((account, column), ids)| {
+                let problem = match self.accounts.get(&normalize_account(&account)) {
+                    Some(false) => "is inactive in",
+                    _ => "is not in",
+                };
+                Issue::error(
+                    None,
+                    format!(
+                        "{} account `{}` {} the chart of accounts (employees {})",
+                        column,
+                        account,
+                        problem,
+                        ids.into_iter().collect::<Vec<String>>().join(", ")
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DistributionCode {
+    pub exp_account: String,
+    pub liab_account: String,
+    pub ot_account: String,
+    pub shift_account: String,
+    pub overtime_schedule: String,
+}
+
+/// Accounts and OT schedule to use for each distribution code, keyed by the `DISTCODE`
+/// column, so supervisors only need to enter the dist code on a timecard.
+#[derive(Debug, Clone, Default)]
+pub struct DistributionCodes {
+    codes: HashMap<String, DistributionCode>,
+}
+
+impl DistributionCodes {
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut table = Self::default();
+
+        for record in records.iter() {
+            let code = match record.get("DISTCODE") {
+                Some(code) => code.to_string(),
+                None => continue,
+            };
+            table.codes.insert(
+                code,
+                DistributionCode {
+                    exp_account: record.get_string("EXPACCT"),
+                    liab_account: record.get_string("LIABACCT"),
+                    ot_account: record.get_string("OTACCT"),
+                    shift_account: record.get_string("SHIFTACCT"),
+                    overtime_schedule: record.get_string("OTSCHED"),
+                },
+            );
+        }
+
+        Ok(table)
+    }
+
+    pub fn get(&self, code: &str) -> Option<&DistributionCode> {
+        self.codes.get(code)
+    }
+
+    /// Fills each employee's blank accounts and OT schedule from their dist code, reporting
+    /// employees with hours whose dist code isn't in the table, and values given on the
+    /// timecard (or employee master) that disagree with the dist code.
+    pub fn apply(&self, employees: &mut [Employee]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for employee in employees.iter_mut() {
+            if employee.dist_code.is_empty() {
+                continue;
+            }
+
+            let has_hours = sum_of_hours(employee.hours.clone()) > 0.0;
+            let code = match self.get(&employee.dist_code) {
+                Some(code) => code,
+                None => {
+                    if has_hours {
+                        issues.push(Issue::error(
+                            Some(&employee.id),
+                            format!(
+                                "distribution code `{}` is not in the distribution code table",
+                                employee.dist_code
+                            ),
+                        ));
+                    }
+                    continue;
+                }
+            };
+
+            let dist_code = employee.dist_code.clone();
+            for (column, value, default) in [
+                ("EXPACCT", &mut employee.exp_account, &code.exp_account),
+                ("LIABACCT", &mut employee.liab_account, &code.liab_account),
+                ("OTACCT", &mut employee.ot_account, &code.ot_account),
+                (
+                    "SHIFTACCT",
+                    &mut employee.shift_account,
+                    &code.shift_account,
+                ),
+                (
+                    "OTSCHED",
+                    &mut employee.overtime_schedule,
+                    &code.overtime_schedule,
+                ),
+            ] {
+                if value.is_empty() {
+                    *value = default.clone();
+                } else if !default.is_empty() && value != default && has_hours {
+                    issues.push(Issue::warning(
+                        Some(&employee.id),
+                        format!(
+                            "{} `{}` differs from `{}` for distribution code `{}`, keeping `{}`",
+                            column, value, default, dist_code, value
+                        ),
+                    ));
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+#[test]
+fn it_checks_segments_against_the_list() {
+    use crate::excel::records::{employee_with, records_of, shift_on};
+
+    let rows = [
+        ["GLSEG", "SEGMENTID", "VALUE", "DESC"],
+        ["1", "000002", "100", "Framing"],
+        ["2", "000003", "VAN", "Vancouver"],
+    ];
+    let list = SegmentList::from_records(records_of(&rows)).unwrap();
+
+    let mut shift = shift_on(0, 8);
+    shift.segments[0].value = "100".to_string();
+    shift.segments[1].value = "100".to_string();
+    let mut employees = vec![employee_with("1001", vec![shift])];
+
+    let issues = list.apply(&mut employees);
+
+    assert_eq!(employees[0].hours[0].segments[0].id, "000002");
+    assert_eq!(employees[0].hours[0].segments[0].desc, "Framing");
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("GL segment 2 value `100`"));
+}
+
+#[test]
+fn it_reports_unknown_and_inactive_accounts() {
+    use crate::excel::records::{employee_with, records_of, shift_on};
+
+    let rows = [
+        ["ACCTFMTTD", "ACCTDESC", "ACTIVESW"],
+        ["6000-100", "Wages", "1"],
+        ["2300-000", "Payroll liabilities", "0"],
+    ];
+    let chart = ChartOfAccounts::from_records(records_of(&rows)).unwrap();
+
+    let mut employee = employee_with("1001", vec![shift_on(0, 8)]);
+    employee.exp_account = "6000100".to_string();
+    employee.ot_account = "6100".to_string();
+    employee.liab_account = "2300-000".to_string();
+
+    let issues = chart.validate(&[employee]);
+
+    assert_eq!(issues.len(), 2);
+    assert!(issues[0]
+        .message
+        .starts_with("LIABACCT account `2300-000` is inactive"));
+    assert!(issues[1]
+        .message
+        .starts_with("OTACCT account `6100` is not in"));
+}
+
+#[test]
+fn it_fills_accounts_from_the_dist_code() {
+    use crate::excel::records::{employee_with, records_of, shift_on};
+
+    let rows = [
+        ["DISTCODE", "EXPACCT", "LIABACCT", "OTACCT", "OTSCHED"],
+        ["SHOP", "6000", "2300", "6100", "STD"],
+    ];
+    let codes = DistributionCodes::from_records(records_of(&rows)).unwrap();
+
+    let mut employee = employee_with("1001", vec![shift_on(0, 8)]);
+    employee.dist_code = "SHOP".to_string();
+    employee.exp_account = "6050".to_string();
+    let mut employees = vec![employee];
+
+    let issues = codes.apply(&mut employees);
+
+    assert_eq!(employees[0].exp_account, "6050");
+    assert_eq!(employees[0].liab_account, "2300");
+    assert_eq!(employees[0].overtime_account(), "6100");
+    assert_eq!(employees[0].overtime_schedule, "STD");
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0]
+        .message
+        .starts_with("EXPACCT `6050` differs from `6000`"));
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::employees::{Employee, Shift};
+use crate::excel::records::{read_records, Record};
+use crate::excel::ExcelError;
+use crate::validation::Issue;
+
+/// The balances Sage keeps for an employee, each with paid and accrued totals on the
+/// timecard header (e.g. `GVACHRSP` and `GVACHRSA`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bank {
+    Vacation,
+    Sick,
+    Comp,
+    Disability,
+}
+
+impl Bank {
+    pub const ALL: [Bank; 4] = [Bank::Vacation, Bank::Sick, Bank::Comp, Bank::Disability];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Bank::Vacation => "vacation",
+            Bank::Sick => "sick",
+            Bank::Comp => "comp time",
+            Bank::Disability => "disability",
+        }
+    }
+
+    /// The part of the header column names for this bank.
+    pub fn column(&self) -> &'static str {
+        match self {
+            Bank::Vacation => "VAC",
+            Bank::Sick => "SICK",
+            Bank::Comp => "COMP",
+            Bank::Disability => "DISI",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_uppercase().as_str() {
+            "VAC" | "VACATION" => Some(Bank::Vacation),
+            "SICK" => Some(Bank::Sick),
+            "COMP" | "COMPTIME" => Some(Bank::Comp),
+            "DISI" | "DISABILITY" => Some(Bank::Disability),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a line's hours are paid out now or added to a bank, written to `PAYORACCR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PayOrAccrue {
+    Pay,
+    Accrue,
+}
+
+impl PayOrAccrue {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PayOrAccrue::Pay => "1",
+            PayOrAccrue::Accrue => "2",
+        }
+    }
+
+    /// The action for a `PAYORACCR` value.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim() {
+            "1" => Some(PayOrAccrue::Pay),
+            "2" => Some(PayOrAccrue::Accrue),
+            _ => None,
+        }
+    }
+
+    /// The suffix of the header columns for this action, `P` or `A`.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            PayOrAccrue::Pay => "P",
+            PayOrAccrue::Accrue => "A",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_uppercase().as_str() {
+            "P" | "PAY" => Some(PayOrAccrue::Pay),
+            "A" | "ACCRUE" | "BANK" => Some(PayOrAccrue::Accrue),
+            _ => None,
+        }
+    }
+}
+
+/// The bank a line's hours count against, and whether they are paid from it or added to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accrual {
+    pub bank: Bank,
+    pub action: PayOrAccrue,
+}
+
+#[derive(Debug, Clone)]
+struct AccrualRule {
+    earning: String,
+    code: String,
+    overtime_schedule: String,
+    accrual: Accrual,
+}
+
+impl AccrualRule {
+    fn from_record(record: &Record) -> Result<Option<Self>, ExcelError> {
+        let earning = record.get_string("EARNDED").to_uppercase();
+        let code = record.get_string("CODE");
+        if earning.is_empty() && code.is_empty() {
+            return Ok(None);
+        }
+
+        let bank = record.get("BANK").unwrap_or_default();
+        let bank = Bank::parse(bank).ok_or_else(|| {
+            ExcelError::Msg(format!(
+                "accrual rule for `{}{}` has `BANK` of `{}`, expected VAC, SICK, COMP or DISI",
+                earning, code, bank
+            ))
+        })?;
+        let action = record.get("ACTION").unwrap_or_default();
+        let action = PayOrAccrue::parse(action).ok_or_else(|| {
+            ExcelError::Msg(format!(
+                "accrual rule for `{}{}` has `ACTION` of `{}`, expected PAY or ACCRUE",
+                earning, code, action
+            ))
+        })?;
+
+        Ok(Some(Self {
+            earning,
+            code,
+            overtime_schedule: record.get_string("OTSCHED"),
+            accrual: Accrual { bank, action },
+        }))
+    }
+
+    fn matches(&self, employee: &Employee, shift: &Shift) -> bool {
+        let by_code = if self.code.is_empty() {
+            self.earning == shift.earning
+        } else {
+            self.code.eq_ignore_ascii_case(&shift.shift_code)
+        };
+
+        by_code
+            && (self.overtime_schedule.is_empty()
+                || self.overtime_schedule == employee.overtime_schedule)
+    }
+}
+
+/// Rules for earnings that are paid from or accrued to a vacation, sick, comp time or
+/// disability bank, with `EARNDED`, `BANK` and `ACTION` (PAY or ACCRUE) columns.
+///
+/// A rule can be limited to employees on one `OTSCHED`, or apply to hours typed with a
+/// `CODE` after them (e.g. `4B` for banked overtime), in which case the hours are moved to
+/// the rule's earning code. The first rule that matches wins.
+#[derive(Debug, Clone, Default)]
+pub struct AccrualRules {
+    rules: Vec<AccrualRule>,
+}
+
+impl AccrualRules {
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut rules = Vec::new();
+        for record in records.iter() {
+            if let Some(rule) = AccrualRule::from_record(record)? {
+                rules.push(rule);
+            }
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Marks every shift a rule matches as paid or accrued, reporting codes that moved hours
+    /// to a rule without an earning code.
+    pub fn apply(&self, employees: &mut [Employee]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for employee in employees.iter_mut() {
+            let mut hours = std::mem::take(&mut employee.hours);
+            for shift in hours.iter_mut() {
+                let rule = match self.rules.iter().find(|rule| rule.matches(employee, shift)) {
+                    Some(rule) => rule,
+                    None => continue,
+                };
+
+                if !rule.code.is_empty() {
+                    if rule.earning.is_empty() {
+                        issues.push(Issue::error(
+                            Some(&employee.id),
+                            format!("accrual code `{}` has no `EARNDED` to pay under", rule.code),
+                        ));
+                        continue;
+                    }
+                    shift.earning = rule.earning.clone();
+                    shift.shift_code.clear();
+                }
+                shift.accrual = Some(rule.accrual);
+            }
+            employee.hours = hours;
+        }
+
+        issues
+    }
+}
+
+/// Hours paid from and accrued to each bank, for the timecard header's totals.
+pub fn bank_hours(shifts: &[Shift]) -> BTreeMap<(Bank, PayOrAccrue), f32> {
+    let mut totals = BTreeMap::new();
+    for shift in shifts.iter() {
+        if let Some(accrual) = shift.accrual {
+            *totals.entry((accrual.bank, accrual.action)).or_insert(0.0) += shift.sum_of_shift();
+        }
+    }
+    totals
+}
+
+#[test]
+fn it_accrues_banked_hours() {
+    use crate::excel::records::{employee_with, records_of, shift_on, test_start};
+    use crate::excel::sage::build;
+    use crate::excel::timecards::DateColumnRange;
+
+    let rows = [
+        ["EARNDED", "CODE", "OTSCHED", "BANK", "ACTION"],
+        ["COMPA", "B", "", "COMP", "ACCRUE"],
+        ["OT", "", "BANK", "COMP", "ACCRUE"],
+        ["VAC", "", "", "VAC", "PAY"],
+    ];
+    let rules = AccrualRules::from_records(records_of(&rows)).unwrap();
+
+    let today = test_start();
+    let shift = |hours: i64, earning: &str, code: &str| {
+        let mut shift = shift_on(0, hours);
+        shift.earning = earning.to_string();
+        shift.shift_code = code.to_string();
+        shift
+    };
+    let mut banking = Employee::new("1001".to_string());
+    banking.overtime_schedule = "BANK".to_string();
+    banking.hours = vec![
+        shift(8, "HRLY", ""),
+        shift(4, "HRLY", "B"),
+        shift(2, "OT", ""),
+        shift(8, "VAC", ""),
+    ];
+    let paid = employee_with("1002", vec![shift(2, "OT", "")]);
+    let mut employees = vec![banking, paid];
+
+    assert!(rules.apply(&mut employees).is_empty());
+    assert_eq!(employees[0].hours[0].accrual, None);
+    assert_eq!(employees[0].hours[1].earning, "COMPA");
+    assert_eq!(employees[0].hours[1].shift_code, "");
+    assert_eq!(employees[1].hours[0].accrual, None);
+
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 4;
+    date_range.start = Some(today);
+    date_range.end = Some(today);
+    let import = build("W1", employees, &date_range).unwrap();
+    assert_eq!(import.header[0].get("GCOMPHRSA"), Some("6"));
+    assert_eq!(import.header[0].get("CVACHRSP"), Some("8"));
+    let codes: Vec<Option<&str>> = import.detail[..4]
+        .iter()
+        .map(|row| row.get("PAYORACCR"))
+        .collect();
+    assert_eq!(codes, vec![Some("2"), None, Some("2"), Some("1")]);
+}
@@ -0,0 +1,237 @@
+use crate::excel::sage::{SageImport, SageRow};
+use crate::lines::LINE_NUMBER_STEP;
+
+/// Columns that identify a timecard line in Sage.
+const KEY_COLUMNS: &[(&str, &str)] = &[
+    ("EMPLOYEE", "NKEMPLOYEE"),
+    ("PEREND", "NKPEREND"),
+    ("TIMECARD", "NKTIMECARD"),
+    ("LINENUM", "NKLINENUM"),
+];
+
+/// Columns that decide whether a corrected line is the same line as one already imported,
+/// rather than a new one, when it has been renumbered.
+const MATCH_COLUMNS: &[&str] = &[
+    "EMPLOYEE",
+    "EARNDED",
+    "EARDEDDATE",
+    "DESC",
+    "SHIFTNUM",
+    "STARTTIME",
+];
+
+/// How Sage applies a line of a correction import, written to `KEYACTION`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAction {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl KeyAction {
+    pub fn code(&self) -> &'static str {
+        match self {
+            KeyAction::Insert => "1",
+            KeyAction::Update => "2",
+            KeyAction::Delete => "3",
+        }
+    }
+}
+
+/// Blank and missing cells are the same, since blank cells aren't kept when a workbook is
+/// read back.
+fn same(a: Option<&str>, b: Option<&str>) -> bool {
+    a.unwrap_or_default().trim() == b.unwrap_or_default().trim()
+}
+
+fn matches(a: &SageRow, b: &SageRow, columns: &[&str]) -> bool {
+    columns
+        .iter()
+        .all(|heading| same(a.get(heading), b.get(heading)))
+}
+
+fn unchanged(a: &SageRow, b: &SageRow) -> bool {
+    a.cells()
+        .zip(b.cells())
+        .filter(|((heading, _), _)| !["LINENUM", "KEYACTION"].contains(heading))
+        .all(|((_, a), (_, b))| same(a, b))
+}
+
+fn line_number(row: &SageRow) -> usize {
+    row.get("LINENUM")
+        .and_then(|n| n.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+/// Points a correction line at the already imported line it changes.
+fn with_action(mut row: SageRow, action: KeyAction, existing: Option<&SageRow>) -> SageRow {
+    row.set("KEYACTION", action.code());
+    if let Some(existing) = existing {
+        for (key, new_key) in KEY_COLUMNS.iter() {
+            row.set(new_key, existing.get(key).unwrap_or_default());
+        }
+    }
+    row
+}
+
+/// Works out the lines that turn an already imported timecard into the corrected one:
+/// updates for lines that changed, inserts for new lines and deletes for lines that are
+/// gone. Lines that didn't change are left out, so re-importing doesn't duplicate them.
+///
+/// Header lines are kept for every employee with a correction, and deleted along with
+/// the employee's last line.
+pub fn corrections(previous: &SageImport, corrected: &SageImport) -> SageImport {
+    let mut import = SageImport {
+        header: Vec::new(),
+        detail: Vec::new(),
+    };
+    let mut matched = vec![false; previous.detail.len()];
+
+    for header in corrected.header.iter() {
+        let id = header.get("EMPLOYEE").unwrap_or_default();
+        let mut next_line = previous
+            .detail
+            .iter()
+            .filter(|row| row.get("EMPLOYEE") == Some(id))
+            .map(line_number)
+            .max()
+            .unwrap_or_default();
+
+        let mut changed = false;
+        for line in corrected
+            .detail
+            .iter()
+            .filter(|row| row.get("EMPLOYEE") == Some(id))
+        {
+            let existing = previous
+                .detail
+                .iter()
+                .enumerate()
+                .find(|(n, row)| !matched[*n] && matches(row, line, MATCH_COLUMNS));
+            match existing {
+                Some((n, row)) => {
+                    matched[n] = true;
+                    if unchanged(row, line) {
+                        continue;
+                    }
+                    let mut update = with_action(line.clone(), KeyAction::Update, Some(row));
+                    update.set("LINENUM", row.get("LINENUM").unwrap_or_default());
+                    import.detail.push(update);
+                }
+                None => {
+                    next_line += LINE_NUMBER_STEP;
+                    let mut insert = with_action(line.clone(), KeyAction::Insert, None);
+                    insert.set("LINENUM", next_line.to_string());
+                    import.detail.push(insert);
+                }
+            }
+            changed = true;
+        }
+
+        let removed = previous
+            .detail
+            .iter()
+            .enumerate()
+            .any(|(n, row)| !matched[n] && row.get("EMPLOYEE") == Some(id));
+        if changed || removed {
+            let action = if previous
+                .header
+                .iter()
+                .any(|h| h.get("EMPLOYEE") == Some(id))
+            {
+                KeyAction::Update
+            } else {
+                KeyAction::Insert
+            };
+            import
+                .header
+                .push(with_action(header.clone(), action, None));
+        }
+    }
+
+    for (n, row) in previous.detail.iter().enumerate() {
+        if matched[n] {
+            continue;
+        }
+        import
+            .detail
+            .push(with_action(row.clone(), KeyAction::Delete, Some(row)));
+
+        let id = row.get("EMPLOYEE");
+        let still_listed = corrected.header.iter().any(|h| h.get("EMPLOYEE") == id)
+            || import.header.iter().any(|h| h.get("EMPLOYEE") == id);
+        if !still_listed {
+            if let Some(header) = previous.header.iter().find(|h| h.get("EMPLOYEE") == id) {
+                import
+                    .header
+                    .push(with_action(header.clone(), KeyAction::Delete, None));
+            }
+        }
+    }
+
+    import
+}
+
+#[test]
+fn it_emits_only_corrected_lines() {
+    use crate::excel::sage::{TIMECARD_DETAIL_HEADERS, TIMECARD_HEADER_HEADERS};
+
+    let header = |id: &str| {
+        let mut row = SageRow::new(TIMECARD_HEADER_HEADERS);
+        row.set("EMPLOYEE", id);
+        row
+    };
+    let line = |id: &str, linenum: &str, date: &str, hours: &str| {
+        let mut row = SageRow::new(TIMECARD_DETAIL_HEADERS);
+        row.set("EMPLOYEE", id);
+        row.set("LINENUM", linenum);
+        row.set("EARNDED", "HRLY");
+        row.set("EARDEDDATE", date);
+        row.set("HOURS", hours);
+        row
+    };
+
+    let previous = SageImport {
+        header: vec![header("1001"), header("1002")],
+        detail: vec![
+            line("1001", "1000", "2021-05-03", "8"),
+            line("1001", "2000", "2021-05-04", "8"),
+            line("1001", "3000", "2021-05-05", "8"),
+            line("1002", "1000", "2021-05-03", "4"),
+        ],
+    };
+    // 1001's second day was corrected, the third removed and a fourth added, 1002 is gone
+    let corrected = SageImport {
+        header: vec![header("1001")],
+        detail: vec![
+            line("1001", "1000", "2021-05-03", "8"),
+            line("1001", "2000", "2021-05-04", "6"),
+            line("1001", "3000", "2021-05-06", "8"),
+        ],
+    };
+
+    let import = corrections(&previous, &corrected);
+    let summary: Vec<(&str, &str, &str)> = import
+        .detail
+        .iter()
+        .map(|row| {
+            (
+                row.get("EMPLOYEE").unwrap(),
+                row.get("LINENUM").unwrap(),
+                row.get("KEYACTION").unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("1001", "2000", "2"),
+            ("1001", "4000", "1"),
+            ("1001", "3000", "3"),
+            ("1002", "1000", "3"),
+        ]
+    );
+    assert_eq!(import.detail[0].get("NKLINENUM"), Some("2000"));
+    assert_eq!(import.header.len(), 2);
+    assert_eq!(import.header[1].get("KEYACTION"), Some("3"));
+}
@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{Date, Utc};
+
+use crate::employees::Employee;
+use crate::excel::timecards::DateColumnRange;
+
+/// How pay is estimated from hours and rates.
+#[derive(Debug, Clone, Copy)]
+pub struct CostRates {
+    /// Hours over this in a week are overtime, as in the summary sheet.
+    pub weekly_overtime: f32,
+    /// What overtime hours are paid at, as a multiple of the shift's rate.
+    pub overtime_multiplier: f64,
+    /// Employer costs (CPP, EI, benefits, ...) as a percentage of gross pay.
+    pub burden_percent: f64,
+}
+
+/// Hours and estimated pay for one employee, dist code or GL account.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostTotals {
+    pub regular_hours: f32,
+    pub overtime_hours: f32,
+    pub gross_pay: f64,
+    pub employer_cost: f64,
+    /// Hours with no rate, which are left out of the pay.
+    pub unpriced_hours: f32,
+}
+
+impl CostTotals {
+    fn add(&mut self, other: &CostTotals) {
+        self.regular_hours += other.regular_hours;
+        self.overtime_hours += other.overtime_hours;
+        self.gross_pay += other.gross_pay;
+        self.employer_cost += other.employer_cost;
+        self.unpriced_hours += other.unpriced_hours;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmployeeCost {
+    pub id: String,
+    pub dist_code: String,
+    pub exp_account: String,
+    pub ot_account: String,
+    pub regular: CostTotals,
+    pub overtime: CostTotals,
+}
+
+impl EmployeeCost {
+    pub fn total(&self) -> CostTotals {
+        let mut total = self.regular.clone();
+        total.add(&self.overtime);
+        total
+    }
+}
+
+/// An estimate of what a pay period will cost before Sage processes it: gross pay from each
+/// shift's hours and rate, plus the employer's share as a percentage of it.
+///
+/// Like the summary sheet, overtime is the regular hours worked over the weekly limit in
+/// each week of the period; Sage still works out the overtime it pays from the employee's
+/// OT schedule. Hours banked instead of paid are left out.
+#[derive(Debug, Clone)]
+pub struct LabourCost {
+    pub employees: Vec<EmployeeCost>,
+}
+
+impl LabourCost {
+    pub fn new(employees: &[Employee], date_range: &DateColumnRange, rates: CostRates) -> Self {
+        let first_day: Option<Date<Utc>> = date_range.start;
+
+        let employees = employees
+            .iter()
+            .filter_map(|employee| {
+                let mut shifts: Vec<_> = employee
+                    .hours
+                    .iter()
+                    .filter(|shift| shift.sum_of_shift() > 0.0 && !shift.is_banked())
+                    .collect();
+                if shifts.is_empty() {
+                    return None;
+                }
+                shifts.sort_by_key(|shift| shift.date);
+
+                let mut cost = EmployeeCost {
+                    id: employee.id.clone(),
+                    dist_code: employee.dist_code.clone(),
+                    exp_account: employee.exp_account.clone(),
+                    ot_account: employee.overtime_account().to_string(),
+                    regular: CostTotals::default(),
+                    overtime: CostTotals::default(),
+                };
+                let mut weeks: BTreeMap<i64, f32> = BTreeMap::new();
+                for shift in shifts {
+                    let week = first_day
+                        .map(|first_day| (shift.date - first_day).num_days().div_euclid(7))
+                        .unwrap_or_default();
+                    let hours = shift.sum_of_shift();
+                    let regular = if shift.counts_toward_overtime() {
+                        let worked = weeks.entry(week).or_default();
+                        let regular = hours.min((rates.weekly_overtime - *worked).max(0.0));
+                        *worked += hours;
+                        regular
+                    } else {
+                        hours
+                    };
+
+                    let rate = employee.shift_rate(shift);
+                    for (overtime, hours) in [(false, regular), (true, hours - regular)] {
+                        if hours <= 0.0 {
+                            continue;
+                        }
+                        let (totals, multiplier) = if overtime {
+                            cost.overtime.overtime_hours += hours;
+                            (&mut cost.overtime, rates.overtime_multiplier)
+                        } else {
+                            cost.regular.regular_hours += hours;
+                            (&mut cost.regular, 1.0)
+                        };
+                        match rate {
+                            Some(rate) => {
+                                let pay = hours as f64 * rate * multiplier;
+                                totals.gross_pay += pay;
+                                totals.employer_cost += pay * (1.0 + rates.burden_percent / 100.0);
+                            }
+                            None => totals.unpriced_hours += hours,
+                        }
+                    }
+                }
+
+                Some(cost)
+            })
+            .collect();
+
+        Self { employees }
+    }
+
+    /// Totals for each dist code, in order.
+    pub fn by_dist_code(&self) -> BTreeMap<&str, CostTotals> {
+        let mut groups: BTreeMap<&str, CostTotals> = BTreeMap::new();
+        for employee in self.employees.iter() {
+            groups
+                .entry(employee.dist_code.as_str())
+                .or_default()
+                .add(&employee.total());
+        }
+        groups
+    }
+
+    /// Totals for each GL account, with regular pay charged to the expense account and
+    /// overtime pay to the OT account.
+    pub fn by_account(&self) -> BTreeMap<&str, CostTotals> {
+        let mut groups: BTreeMap<&str, CostTotals> = BTreeMap::new();
+        for employee in self.employees.iter() {
+            groups
+                .entry(employee.exp_account.as_str())
+                .or_default()
+                .add(&employee.regular);
+            groups
+                .entry(employee.ot_account.as_str())
+                .or_default()
+                .add(&employee.overtime);
+        }
+        groups
+    }
+
+    pub fn total(&self) -> CostTotals {
+        let mut total = CostTotals::default();
+        for employee in self.employees.iter() {
+            total.add(&employee.total());
+        }
+        total
+    }
+
+    /// Ids of the employees with hours that have no rate, so their cost is understated.
+    pub fn unpriced(&self) -> Vec<&str> {
+        self.employees
+            .iter()
+            .filter(|employee| employee.total().unpriced_hours > 0.0)
+            .map(|employee| employee.id.as_str())
+            .collect()
+    }
+
+    /// Writes the estimate as a CSV file, with a row per employee, then per dist code, then
+    /// per GL account, then the period's total.
+    pub fn write_csv(&self, path: &Path) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record([
+            "Group",
+            "Key",
+            "Regular Hours",
+            "Overtime Hours",
+            "Unpriced Hours",
+            "Gross Pay",
+            "Employer Cost",
+        ])?;
+
+        let mut write = |group: &str, key: &str, totals: &CostTotals| {
+            writer.write_record([
+                group.to_string(),
+                key.to_string(),
+                format!("{:.2}", totals.regular_hours),
+                format!("{:.2}", totals.overtime_hours),
+                format!("{:.2}", totals.unpriced_hours),
+                format!("{:.2}", totals.gross_pay),
+                format!("{:.2}", totals.employer_cost),
+            ])
+        };
+        for employee in self.employees.iter() {
+            write("Employee", &employee.id, &employee.total())?;
+        }
+        for (dist_code, totals) in self.by_dist_code() {
+            write("Dist Code", dist_code, &totals)?;
+        }
+        for (account, totals) in self.by_account() {
+            write("GL Account", account, &totals)?;
+        }
+        write("Total", "", &self.total())?;
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn it_estimates_pay_with_overtime() {
+    use crate::employees::Shift;
+    use chrono::{Duration, TimeZone};
+
+    let start = Utc.ymd(2021, 5, 3);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(6));
+
+    let mut employee = Employee::new("1001".to_string());
+    employee.dist_code = "SHOP".to_string();
+    employee.exp_account = "6000".to_string();
+    employee.ot_account = "6100".to_string();
+    employee.rate = Some(20.0);
+    // 44 hours, the last day on a job paid at 30
+    for day in 0..5 {
+        let mut shift = Shift::new(4, Duration::hours(8), start + Duration::days(day));
+        if day == 4 {
+            shift.duration = Duration::hours(12);
+            shift.rate = Some(30.0);
+        }
+        employee.hours.push(shift);
+    }
+    let mut unpriced = Employee::new("1002".to_string());
+    unpriced.dist_code = "SHOP".to_string();
+    unpriced.exp_account = "6000".to_string();
+    unpriced
+        .hours
+        .push(Shift::new(4, Duration::hours(5), start));
+
+    let cost = LabourCost::new(
+        &[employee, unpriced],
+        &date_range,
+        CostRates {
+            weekly_overtime: 40.0,
+            overtime_multiplier: 1.5,
+            burden_percent: 10.0,
+        },
+    );
+
+    let total = cost.employees[0].total();
+    assert_eq!(total.regular_hours, 40.0);
+    assert_eq!(total.overtime_hours, 4.0);
+    // 32 x 20 + 8 x 30 + 4 x 45
+    assert!((total.gross_pay - 1060.0).abs() < 1e-9);
+    assert!((total.employer_cost - 1166.0).abs() < 1e-9);
+    assert_eq!(cost.unpriced(), vec!["1002"]);
+
+    let accounts = cost.by_account();
+    assert!((accounts["6100"].gross_pay - 180.0).abs() < 1e-9);
+    assert_eq!(accounts["6000"].unpriced_hours, 5.0);
+    assert_eq!(cost.by_dist_code()["SHOP"].regular_hours, 45.0);
+}
+
+#[test]
+fn it_leaves_leave_and_banked_hours_out_of_overtime() {
+    use crate::accruals::{Accrual, Bank, PayOrAccrue};
+    use crate::employees::Shift;
+    use chrono::{Duration, TimeZone};
+
+    let start = Utc.ymd(2021, 5, 3);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(6));
+
+    let mut employee = Employee::new("1001".to_string());
+    employee.rate = Some(20.0);
+    // 36 hours worked, 8 hours of vacation and 4 hours banked as comp time
+    for day in 0..4 {
+        let shift = Shift::new(4, Duration::hours(9), start + Duration::days(day));
+        employee.hours.push(shift);
+    }
+    let mut vacation = Shift::new(8, Duration::hours(8), start + Duration::days(4));
+    vacation.earning = "VAC".to_string();
+    employee.hours.push(vacation);
+    let mut banked = Shift::new(9, Duration::hours(4), start + Duration::days(5));
+    banked.earning = "COMPA".to_string();
+    banked.accrual = Some(Accrual {
+        bank: Bank::Comp,
+        action: PayOrAccrue::Accrue,
+    });
+    employee.hours.push(banked);
+
+    let cost = LabourCost::new(
+        &[employee],
+        &date_range,
+        CostRates {
+            weekly_overtime: 40.0,
+            overtime_multiplier: 1.5,
+            burden_percent: 0.0,
+        },
+    );
+
+    let total = cost.employees[0].total();
+    assert_eq!(total.regular_hours, 44.0);
+    assert_eq!(total.overtime_hours, 0.0);
+    assert!((total.gross_pay - 880.0).abs() < 1e-9);
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::export::{EmployeeHours, PayPeriod};
+
+/// Hours closer together than this are treated as unchanged, to ignore float rounding.
+const HOURS_TOLERANCE: f32 = 0.005;
+
+/// A difference between two versions of a pay period.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        employee: String,
+        hours: f32,
+    },
+    Removed {
+        employee: String,
+        hours: f32,
+    },
+    Hours {
+        employee: String,
+        date: String,
+        old: f32,
+        new: f32,
+    },
+    Field {
+        employee: String,
+        field: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { employee, hours } => {
+                write!(f, "+ employee `{}` added with {:.2} hours", employee, hours)
+            }
+            Change::Removed { employee, hours } => {
+                write!(
+                    f,
+                    "- employee `{}` removed, had {:.2} hours",
+                    employee, hours
+                )
+            }
+            Change::Hours {
+                employee,
+                date,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ employee `{}` {}: {:.2} -> {:.2} hours",
+                employee, date, old, new
+            ),
+            Change::Field {
+                employee,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ employee `{}` {}: `{}` -> `{}`",
+                employee, field, old, new
+            ),
+        }
+    }
+}
+
+fn daily_hours(employee: &EmployeeHours) -> BTreeMap<&str, f32> {
+    let mut days = BTreeMap::new();
+    for shift in employee.shifts.iter() {
+        *days.entry(shift.date.as_str()).or_insert(0.0) += shift.hours;
+    }
+    days
+}
+
+fn earning_codes(employee: &EmployeeHours) -> String {
+    let mut codes: Vec<&str> = employee
+        .shifts
+        .iter()
+        .map(|shift| shift.earning.as_str())
+        .collect();
+    codes.sort_unstable();
+    codes.dedup();
+    codes.join(", ")
+}
+
+/// Lists the employees added and removed between two pay periods, and for the employees in
+/// both, the days whose hours changed and any codes or accounts that changed.
+pub fn diff(old: &PayPeriod, new: &PayPeriod) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for before in old.employees.iter() {
+        let after = match new.employees.iter().find(|e| e.id == before.id) {
+            Some(after) => after,
+            None => {
+                changes.push(Change::Removed {
+                    employee: before.id.clone(),
+                    hours: before.total_hours,
+                });
+                continue;
+            }
+        };
+
+        let fields = [
+            ("dist code", &before.dist_code, &after.dist_code),
+            (
+                "OT schedule",
+                &before.overtime_schedule,
+                &after.overtime_schedule,
+            ),
+            ("expense account", &before.exp_account, &after.exp_account),
+            ("OT account", &before.ot_account, &after.ot_account),
+            (
+                "liability account",
+                &before.liab_account,
+                &after.liab_account,
+            ),
+            ("shift account", &before.shift_account, &after.shift_account),
+        ];
+        for (field, old, new) in fields.iter() {
+            if old != new {
+                changes.push(Change::Field {
+                    employee: before.id.clone(),
+                    field: field.to_string(),
+                    old: old.to_string(),
+                    new: new.to_string(),
+                });
+            }
+        }
+        let (old_codes, new_codes) = (earning_codes(before), earning_codes(after));
+        if old_codes != new_codes {
+            changes.push(Change::Field {
+                employee: before.id.clone(),
+                field: "earning codes".to_string(),
+                old: old_codes,
+                new: new_codes,
+            });
+        }
+
+        let (old_days, new_days) = (daily_hours(before), daily_hours(after));
+        let mut dates: Vec<&str> = old_days.keys().chain(new_days.keys()).copied().collect();
+        dates.sort_unstable();
+        dates.dedup();
+        for date in dates {
+            let old = old_days.get(date).copied().unwrap_or_default();
+            let new = new_days.get(date).copied().unwrap_or_default();
+            if (old - new).abs() > HOURS_TOLERANCE {
+                changes.push(Change::Hours {
+                    employee: before.id.clone(),
+                    date: date.to_string(),
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+
+    for after in new.employees.iter() {
+        if !old.employees.iter().any(|e| e.id == after.id) {
+            changes.push(Change::Added {
+                employee: after.id.clone(),
+                hours: after.total_hours,
+            });
+        }
+    }
+
+    changes
+}
+
+#[test]
+fn it_diffs_pay_periods() {
+    use crate::employees::{Employee, Shift};
+    use crate::excel::timecards::DateColumnRange;
+    use chrono::{Duration, TimeZone, Utc};
+
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 5;
+    date_range.start = Some(Utc.ymd(2021, 5, 3));
+    date_range.end = Some(Utc.ymd(2021, 5, 3) + Duration::days(1));
+
+    let employee = |id: &str, account: &str, hours: i64| {
+        let mut e = Employee::new(id.to_string());
+        e.exp_account = account.to_string();
+        e.hours
+            .push(Shift::new(4, Duration::hours(hours), Utc.ymd(2021, 5, 3)));
+        e
+    };
+    let old = PayPeriod::new(
+        "Week 1",
+        &date_range,
+        &[employee("1001", "6000", 8), employee("1002", "6000", 8)],
+        &[],
+    )
+    .unwrap();
+    let new = PayPeriod::new(
+        "Week 1",
+        &date_range,
+        &[employee("1001", "6100", 6), employee("1003", "6000", 4)],
+        &[],
+    )
+    .unwrap();
+
+    let changes = diff(&old, &new);
+    assert_eq!(changes.len(), 5);
+    assert!(matches!(&changes[0], Change::Field { field, .. } if field == "expense account"));
+    // the OT account falls back to the expense account
+    assert!(matches!(&changes[1], Change::Field { field, .. } if field == "OT account"));
+    assert!(matches!(changes[2], Change::Hours { old, new, .. } if old == 8.0 && new == 6.0));
+    assert_eq!(
+        changes[3],
+        Change::Removed {
+            employee: "1002".to_string(),
+            hours: 8.0
+        }
+    );
+    assert_eq!(
+        changes[4].to_string(),
+        "+ employee `1003` added with 4.00 hours"
+    );
+}
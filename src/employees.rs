@@ -1,5 +1,10 @@
 extern crate chrono;
 
+use crate::accruals::{Accrual, PayOrAccrue};
+
+/// The Sage earning code for regular hourly pay.
+pub const REGULAR_EARNING: &str = "HRLY";
+
 #[derive(Clone)]
 pub struct Employee {
     pub id: String,
@@ -7,6 +12,26 @@ pub struct Employee {
     pub overtime_schedule: String,
     pub dist_code: String,
     pub exp_account: String,
+    /// Overtime expense account, the expense account is used when blank.
+    pub ot_account: String,
+    pub liab_account: String,
+    pub shift_account: String,
+    pub last_name: String,
+    pub first_name: String,
+    pub hire_date: Option<chrono::Date<chrono::Utc>>,
+    pub pay_frequency: String,
+    pub part_time: String,
+    pub shift_schedule: String,
+    pub status: String,
+    pub inactive_date: Option<chrono::Date<chrono::Utc>>,
+    pub fire_date: Option<chrono::Date<chrono::Utc>>,
+    /// The Sage timecard id and description the hours are imported under.
+    pub timecard: String,
+    pub timecard_desc: String,
+    /// Hourly rate from the master file, for lines that don't have a rate of their own.
+    pub rate: Option<f64>,
+    /// Whether rates are written to the detail lines, overriding the rates set up in Sage.
+    pub override_rate: bool,
 }
 
 impl Employee {
@@ -17,21 +42,104 @@ impl Employee {
             overtime_schedule: String::new(),
             dist_code: String::new(),
             exp_account: String::new(),
+            ot_account: String::new(),
+            liab_account: String::new(),
+            shift_account: String::new(),
+            last_name: String::new(),
+            first_name: String::new(),
+            hire_date: None,
+            pay_frequency: String::new(),
+            part_time: String::new(),
+            shift_schedule: String::new(),
+            status: String::new(),
+            inactive_date: None,
+            fire_date: None,
+            timecard: String::new(),
+            timecard_desc: String::new(),
+            rate: None,
+            override_rate: false,
+        }
+    }
+
+    pub fn overtime_account(&self) -> &str {
+        if self.ot_account.is_empty() {
+            &self.exp_account
+        } else {
+            &self.ot_account
         }
     }
+
+    /// The hourly rate a shift is paid at: its own rate, or else the employee's.
+    pub fn shift_rate(&self, shift: &Shift) -> Option<f64> {
+        shift.rate.or(self.rate)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Shift {
     pub col: usize,
     pub duration: chrono::Duration,
     pub date: chrono::Date<chrono::Utc>,
+    /// The job or project these hours are costed to, blank when not job costed.
+    pub job: String,
+    /// Values for the detail line's `GLSEG1` to `GLSEG3` columns.
+    pub segments: [GlSegment; 3],
+    /// Clock times, when the timecard gave them instead of a number of hours.
+    pub start: Option<chrono::NaiveTime>,
+    pub stop: Option<chrono::NaiveTime>,
+    /// Shift code typed after the hours (e.g. the `N` in `8N`), blank when not given.
+    pub shift_code: String,
+    /// The Sage shift number for differential pay, blank for regular hours.
+    pub shift_number: String,
+    /// The Sage earning code the hours are paid under.
+    pub earning: String,
+    /// Hourly rate for the row or job these hours were worked on, when it has its own rate.
+    pub rate: Option<f64>,
+    /// The bank these hours are paid from or accrued to, when they count against one.
+    pub accrual: Option<Accrual>,
 }
 
 impl Shift {
+    pub fn new(col: usize, duration: chrono::Duration, date: chrono::Date<chrono::Utc>) -> Self {
+        Self {
+            col,
+            duration,
+            date,
+            job: String::new(),
+            segments: Default::default(),
+            start: None,
+            stop: None,
+            shift_code: String::new(),
+            shift_number: String::new(),
+            earning: REGULAR_EARNING.to_string(),
+            rate: None,
+            accrual: None,
+        }
+    }
+
     pub fn sum_of_shift(&self) -> f32 {
         self.duration.num_minutes() as f32 / 60.0
     }
+
+    /// Whether the hours are added to a bank rather than paid this period.
+    pub fn is_banked(&self) -> bool {
+        matches!(self.accrual, Some(accrual) if accrual.action == PayOrAccrue::Accrue)
+    }
+
+    /// Whether the hours count toward weekly overtime: regular hours worked, including
+    /// shift differential pieces, but not leave, holiday pay or hours that are banked.
+    pub fn counts_toward_overtime(&self) -> bool {
+        self.earning == REGULAR_EARNING && !self.is_banked()
+    }
+}
+
+/// A GL segment override (e.g. department or location) for a detail line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GlSegment {
+    pub value: String,
+    /// The Sage GL segment id the value belongs to, once checked against the segment list.
+    pub id: String,
+    pub desc: String,
 }
 
 pub fn sum_of_hours(shifts: Vec<Shift>) -> f32 {
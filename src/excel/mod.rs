@@ -1,6 +1,10 @@
+pub mod records;
 pub mod sage;
+pub mod sage_import;
+pub mod summary;
 pub mod timecards;
 
+use chrono::{Date, Duration, NaiveDate, Utc};
 use thiserror::Error;
 use unicode_segmentation::UnicodeSegmentation;
 
@@ -18,6 +22,26 @@ pub enum ExcelError {
     Msg(String),
     #[error("worksheet not found '{0}'")]
     WorksheetNotFound(String),
+    #[error("csv error")]
+    Csv(csv::Error),
+}
+
+impl From<calamine::Error> for ExcelError {
+    fn from(err: calamine::Error) -> Self {
+        match err {
+            calamine::Error::Io(err) => ExcelError::Io(err),
+            calamine::Error::Xlsx(err) => ExcelError::Xlsx(err),
+            calamine::Error::Msg(msg) => ExcelError::Msg(msg.to_string()),
+            _ => ExcelError::Unexpected,
+        }
+    }
+}
+
+/// Converts an excel date serial (days since Jan 1, 1900) into a date.
+pub fn date_from_serial(serial: f64) -> Date<Utc> {
+    let date = Date::<Utc>::from_utc(NaiveDate::from_ymd(1900, 1, 1), Utc);
+    // Excel counts Jan 1, 1900 as day 1 and believes 1900 was a leap year.
+    date + Duration::days((serial.round() - 2.0) as i64)
 }
 
 pub fn to_column_letter(col: i32) -> String {
@@ -0,0 +1,196 @@
+use super::{date_from_serial, ExcelError};
+use calamine::{DataType, Reader};
+use chrono::{Date, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::path::Path;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A single row of a lookup table (CSV or spreadsheet), keyed by its column heading.
+///
+/// Headings are matched case-insensitively so that exports from Sage (`EMPLOYEE`,
+/// `LASTNAME`, ...) and hand-maintained files (`Employee`, `LastName`, ...) both work.
+#[derive(Debug, Clone, Default)]
+pub struct Record {
+    fields: HashMap<String, String>,
+}
+
+impl Record {
+    /// The trimmed value under `heading`, or `None` when the column is missing or blank.
+    pub fn get(&self, heading: &str) -> Option<&str> {
+        self.fields
+            .get(&heading.to_uppercase())
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn get_string(&self, heading: &str) -> String {
+        self.get(heading).unwrap_or_default().to_string()
+    }
+
+    pub fn get_number(&self, heading: &str) -> Result<Option<f64>, ExcelError> {
+        match self.get(heading) {
+            Some(value) => value.parse::<f64>().map(Some).map_err(|_| {
+                ExcelError::Msg(format!(
+                    "`{}` is not a valid number for column `{}`",
+                    value, heading
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses a date written either as `YYYY-MM-DD` or as an excel date serial.
+    pub fn get_date(&self, heading: &str) -> Result<Option<Date<Utc>>, ExcelError> {
+        let value = match self.get(heading) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        if let Ok(naive) = NaiveDate::parse_from_str(value, DATE_FORMAT) {
+            return Ok(Some(Date::<Utc>::from_utc(naive, Utc)));
+        }
+        if let Ok(serial) = value.parse::<f64>() {
+            return Ok(Some(date_from_serial(serial)));
+        }
+
+        Err(ExcelError::Msg(format!(
+            "`{}` is not a valid date for column `{}`, expected the format YYYY-MM-DD",
+            value, heading
+        )))
+    }
+}
+
+/// Reads every row of a `.csv` file, or of the first worksheet of any workbook calamine
+/// understands, using the first row as headings.
+pub fn read_records(path: &Path) -> Result<Vec<Record>, ExcelError> {
+    let is_csv = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false);
+    let rows = if is_csv {
+        read_csv_rows(path)?
+    } else {
+        read_workbook_rows(path)?
+    };
+
+    Ok(records_from_rows(rows))
+}
+
+pub(crate) fn records_from_rows(rows: Vec<Vec<String>>) -> Vec<Record> {
+    let mut rows = rows.into_iter();
+    let headings: Vec<String> = match rows.next() {
+        Some(headings) => headings.iter().map(|h| h.trim().to_uppercase()).collect(),
+        None => return Vec::new(),
+    };
+
+    rows.filter(|row| row.iter().any(|cell| !cell.trim().is_empty()))
+        .map(|row| Record {
+            fields: headings.iter().cloned().zip(row).collect(),
+        })
+        .collect()
+}
+
+/// Records from rows written out in a test, the first being the headings.
+#[cfg(test)]
+pub(crate) fn records_of<'a, R: AsRef<[&'a str]>>(rows: &[R]) -> Vec<Record> {
+    records_from_rows(
+        rows.iter()
+            .map(|row| row.as_ref().iter().map(|cell| cell.to_string()).collect())
+            .collect(),
+    )
+}
+
+/// The first day of the pay period timecards in tests cover, Monday May 3, 2021.
+#[cfg(test)]
+pub(crate) fn test_start() -> Date<Utc> {
+    use chrono::TimeZone;
+
+    Utc.ymd(2021, 5, 3)
+}
+
+/// Regular hours on a day of the test pay period, in that day's column when the dates
+/// start in column `E`.
+#[cfg(test)]
+pub(crate) fn shift_on(day: i64, hours: i64) -> crate::employees::Shift {
+    use chrono::Duration;
+
+    let date = test_start() + Duration::days(day);
+    crate::employees::Shift::new(4 + day as usize, Duration::hours(hours), date)
+}
+
+/// An employee with the given shifts.
+#[cfg(test)]
+pub(crate) fn employee_with(
+    id: &str,
+    shifts: Vec<crate::employees::Shift>,
+) -> crate::employees::Employee {
+    let mut employee = crate::employees::Employee::new(id.to_string());
+    employee.hours = shifts;
+    employee
+}
+
+fn read_csv_rows(path: &Path) -> Result<Vec<Vec<String>>, ExcelError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .map_err(ExcelError::Csv)?;
+
+    let mut rows = Vec::new();
+    for result in reader.records() {
+        let record = result.map_err(ExcelError::Csv)?;
+        rows.push(record.iter().map(String::from).collect());
+    }
+
+    Ok(rows)
+}
+
+fn read_workbook_rows(path: &Path) -> Result<Vec<Vec<String>>, ExcelError> {
+    let mut workbook = calamine::open_workbook_auto(path).map_err(ExcelError::from)?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| ExcelError::WorksheetNotFound(path.display().to_string()))?
+        .map_err(ExcelError::from)?;
+
+    Ok(range
+        .rows()
+        .map(|row| row.iter().map(cell_to_string).collect())
+        .collect())
+}
+
+pub(crate) fn cell_to_string(cell: &DataType) -> String {
+    match cell {
+        DataType::String(txt) => txt.clone(),
+        DataType::Int(i) => i.to_string(),
+        // Codes and account numbers are usually typed as numbers, which excel stores as floats.
+        DataType::Float(f) if f.fract() == 0.0 => (*f as i64).to_string(),
+        DataType::Float(f) => f.to_string(),
+        // Times of day are stored as a fraction of a day, with no date part.
+        DataType::DateTime(f) if *f < 1.0 => {
+            let minutes = (f * 24.0 * 60.0).round() as i64;
+            format!("{:02}:{:02}", minutes / 60, minutes % 60)
+        }
+        DataType::DateTime(f) => date_from_serial(*f).format(DATE_FORMAT).to_string(),
+        DataType::Bool(b) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+#[test]
+fn it_reads_records_by_heading() {
+    let records = records_from_rows(vec![
+        vec!["Employee".to_string(), "HireDate".to_string()],
+        vec!["1001".to_string(), "2021-05-03".to_string()],
+        vec![String::new(), String::new()],
+        vec!["1002".to_string(), "44319".to_string()],
+    ]);
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].get("EMPLOYEE"), Some("1001"));
+    assert_eq!(records[0].get("LASTNAME"), None);
+    assert_eq!(
+        records[0].get_date("HIREDATE").unwrap(),
+        records[1].get_date("HIREDATE").unwrap()
+    );
+}
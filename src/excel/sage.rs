@@ -1,8 +1,15 @@
 use std::convert::TryInto;
+use std::io;
 
+use crate::accruals::bank_hours;
 use crate::employees::{sum_of_hours, Employee};
 use crate::excel::timecards::DateColumnRange;
+use crate::excel::summary::write_summary;
 use crate::excel::to_column_letter;
+use crate::lines::build_lines;
+use crate::summary::Summary;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
 use thiserror::Error;
 use xlsxwriter::{Workbook, XlsxError};
 
@@ -15,8 +22,9 @@ pub enum ExcelWriteError {
 }
 
 const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M:%S";
 
-const TIMECARD_HEADER_HEADERS: &[&str] = &[
+pub const TIMECARD_HEADER_HEADERS: &[&str] = &[
     "EMPLOYEE",
     "PEREND",
     "TIMECARD",
@@ -31,7 +39,7 @@ const TIMECARD_HEADER_HEADERS: &[&str] = &[
     "CVACHRSP",
     "CVACHRSA",
     "CSICKHRSP",
-    "CSICKHRSP",
+    "CSICKHRSA",
     "CCOMPHRSP",
     "CCOMPHRSA",
     "CVACAMTP",
@@ -93,7 +101,7 @@ const TIMECARD_HEADER_HEADERS: &[&str] = &[
     "SRCEAPPL",
 ];
 
-const TIMECARD_DETAIL_HEADERS: &[&str] = &[
+pub const TIMECARD_DETAIL_HEADERS: &[&str] = &[
     "EMPLOYEE",
     "PEREND",
     "TIMECARD",
@@ -162,30 +170,100 @@ const TIMECARD_DETAIL_HEADERS: &[&str] = &[
     "DEFRRATE",
 ];
 
-pub fn generate(
-    workbook: Workbook,
-    payperiod: &str,
-    employees: Vec<Employee>,
-    date_range: DateColumnRange,
-) -> Result<(), ExcelWriteError> {
-    let mut sheet_header = workbook
-        .add_worksheet(Some("Timecard_Header"))
-        .map_err(ExcelWriteError::Xlsx)?;
-    for (i, heading) in TIMECARD_HEADER_HEADERS.iter().enumerate() {
-        sheet_header
-            .write_string(0, i as u16, heading, None)
-            .map_err(ExcelWriteError::Xlsx)?;
+/// One row of a Sage import sheet, holding the values set for each of the sheet's columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SageRow {
+    headers: &'static [&'static str],
+    values: Vec<Option<String>>,
+}
+
+impl SageRow {
+    pub fn new(headers: &'static [&'static str]) -> Self {
+        Self {
+            headers,
+            values: vec![None; headers.len()],
+        }
     }
 
-    let mut sheet_detail = workbook
-        .add_worksheet(Some("Timecard_Detail"))
-        .map_err(ExcelWriteError::Xlsx)?;
-    for (i, heading) in TIMECARD_DETAIL_HEADERS.iter().enumerate() {
-        sheet_detail
-            .write_string(0, i as u16, heading, None)
-            .map_err(ExcelWriteError::Xlsx)?;
+    fn column(&self, heading: &str) -> usize {
+        self.headers
+            .iter()
+            .position(|h| *h == heading)
+            .unwrap_or_else(|| panic!("`{}` is not a Sage timecard column", heading))
+    }
+
+    pub fn set(&mut self, heading: &str, value: impl Into<String>) {
+        let col = self.column(heading);
+        self.values[col] = Some(value.into());
+    }
+
+    pub fn get(&self, heading: &str) -> Option<&str> {
+        self.values[self.column(heading)].as_deref()
+    }
+
+    /// Builds a row from the headings of a sheet that was read back and the values under
+    /// them, leaving blank cells unset. Repeated headings are matched to columns in order.
+    pub fn from_headings(
+        headers: &'static [&'static str],
+        headings: &[String],
+        values: Vec<String>,
+    ) -> Result<Self, String> {
+        let mut row = Self::new(headers);
+        for (i, (heading, value)) in headings.iter().zip(values).enumerate() {
+            // Unknown columns are only a problem when they hold something, so workbooks
+            // with a heading that has since been corrected can still be read.
+            if heading.trim().is_empty() || value.trim().is_empty() {
+                continue;
+            }
+            let nth = headings[..i].iter().filter(|h| *h == heading).count();
+            let col = headers
+                .iter()
+                .enumerate()
+                .filter(|(_, h)| *h == heading)
+                .map(|(col, _)| col)
+                .nth(nth)
+                .ok_or_else(|| format!("`{}` is not a Sage timecard column", heading))?;
+            row.values[col] = Some(value);
+        }
+
+        Ok(row)
+    }
+
+    /// Every column of the sheet in order, with its value if one was set.
+    pub fn cells(&self) -> impl Iterator<Item = (&'static str, Option<&str>)> + '_ {
+        self.headers
+            .iter()
+            .copied()
+            .zip(self.values.iter().map(|value| value.as_deref()))
+    }
+}
+
+impl Serialize for SageRow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (heading, value) in self.cells() {
+            if let Some(value) = value {
+                map.serialize_entry(heading, value)?;
+            }
+        }
+        map.end()
     }
+}
 
+/// The rows of a Sage timecard import, one sheet per field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SageImport {
+    #[serde(rename = "Timecard_Header")]
+    pub header: Vec<SageRow>,
+    #[serde(rename = "Timecard_Detail")]
+    pub detail: Vec<SageRow>,
+}
+
+pub fn build(
+    payperiod: &str,
+    employees: Vec<Employee>,
+    date_range: &DateColumnRange,
+) -> Result<SageImport, ExcelWriteError> {
     let (_, end) = date_range.range().ok_or(ExcelWriteError::Unexpected)?;
     let end_formatted = end.format(DATE_FORMAT).to_string(); // 2021-05-08 12:00:00 AM
 
@@ -193,113 +271,277 @@ pub fn generate(
         .into_iter()
         .filter(|e| sum_of_hours(e.hours.clone()) > 0.0)
         .collect();
-    for (i, employee) in employees.iter().enumerate() {
-        let row = i as u32 + 1;
+
+    let mut import = SageImport {
+        header: Vec::new(),
+        detail: Vec::new(),
+    };
+    for employee in employees.iter() {
+        let timecard = if employee.timecard.is_empty() {
+            payperiod
+        } else {
+            &employee.timecard
+        };
 
         // Timecard_Header
-        sheet_header
-            .write_string(row, 0, &employee.id, None)
-            .map_err(ExcelWriteError::Xlsx)?;
-        sheet_header
-            .write_string(row, 1, &end_formatted, None)
-            .map_err(ExcelWriteError::Xlsx)?;
-        sheet_header
-            .write_string(row, 2, payperiod, None)
+        let mut header = SageRow::new(TIMECARD_HEADER_HEADERS);
+        header.set("EMPLOYEE", &employee.id);
+        header.set("PEREND", &end_formatted);
+        header.set("TIMECARD", timecard);
+        if !employee.timecard_desc.is_empty() {
+            header.set("TCARDDESC", &employee.timecard_desc);
+        }
+
+        let format_date = |date: Option<chrono::Date<chrono::Utc>>| {
+            date.map(|date| date.format(DATE_FORMAT).to_string())
+                .unwrap_or_default()
+        };
+        header.set("LASTNAME", &employee.last_name);
+        header.set("FIRSTNAME", &employee.first_name);
+        header.set("HIREDATE", format_date(employee.hire_date));
+        header.set("FIREDATE", format_date(employee.fire_date));
+        header.set("PAYFREQ", &employee.pay_frequency);
+        header.set("PARTTIME", &employee.part_time);
+        header.set("SHIFTSCHED", &employee.shift_schedule);
+        header.set("STATUS", &employee.status);
+        header.set("INACTDATE", format_date(employee.inactive_date));
+
+        let lines = build_lines(&employee.hours);
+
+        let shift_hours: f32 = lines
+            .iter()
+            .filter(|line| !line.shift.shift_number.is_empty())
+            .map(|line| line.shift.sum_of_shift())
+            .sum();
+        if shift_hours > 0.0 {
+            header.set("CSHIFTHRS", format!("{}", shift_hours));
+            header.set("GSHIFTHRS", format!("{}", shift_hours));
+        }
+
+        for ((bank, action), hours) in bank_hours(&employee.hours) {
+            for prefix in ["C", "G"] {
+                let column = format!("{}{}HRS{}", prefix, bank.column(), action.suffix());
+                header.set(&column, format!("{}", hours));
+            }
+        }
+
+        import.header.push(header);
+
+        // Timecard_Detail
+        for line in lines.iter() {
+            let shift = &line.shift;
+            let mut detail = SageRow::new(TIMECARD_DETAIL_HEADERS);
+            detail.set("EMPLOYEE", &employee.id);
+            detail.set("PEREND", &end_formatted);
+            detail.set("TIMECARD", timecard);
+            detail.set("LINENUM", line.number.to_string());
+            detail.set("CATEGORY", "2");
+            detail.set("EARNDED", &shift.earning);
+            detail.set("EARDEDDATE", shift.date.format(DATE_FORMAT).to_string());
+            detail.set("HOURS", format!("{}", shift.sum_of_shift()));
+            detail.set("EXPACCT", &employee.exp_account);
+            detail.set("LIABACCT", &employee.liab_account);
+            detail.set("OTACCT", employee.overtime_account());
+            detail.set("SHIFTACCT", &employee.shift_account);
+            detail.set("OTSCHED", &employee.overtime_schedule);
+            detail.set("DAYS", "1");
+            detail.set("DISTCODE", &employee.dist_code);
+
+            if !shift.shift_number.is_empty() {
+                detail.set("SHIFTSCHED", &employee.shift_schedule);
+                detail.set("SHIFTNUM", &shift.shift_number);
+            }
+            if let Some(accrual) = shift.accrual {
+                detail.set("PAYORACCR", accrual.action.code());
+            }
+            if let (Some(start), Some(stop)) = (shift.start, shift.stop) {
+                detail.set("STARTTIME", start.format(TIME_FORMAT).to_string());
+                detail.set("STOPTIME", stop.format(TIME_FORMAT).to_string());
+            }
+
+            for (n, segment) in shift.segments.iter().enumerate() {
+                if segment.value.is_empty() {
+                    continue;
+                }
+
+                detail.set(&format!("GLSEG{}", n + 1), &segment.value);
+                detail.set(&format!("GLSEGID{}", n + 1), &segment.id);
+                detail.set(&format!("GLSEGDESC{}", n + 1), &segment.desc);
+            }
+
+            // Rates are only written when they should override the rates set up in Sage.
+            if employee.override_rate {
+                if let Some(rate) = employee.shift_rate(shift) {
+                    detail.set("RATE", format!("{}", rate));
+                    detail.set("RRATEOVER", "1");
+                    detail.set("RRATE", format!("{}", rate));
+                }
+                if let Some(rate) = employee.rate {
+                    detail.set("DEFRRATE", format!("{}", rate));
+                }
+            }
+
+            // The job is only descriptive. Sage needs a contract, project and category for
+            // each job allocation, so `SWALLOCJOB`, `JOBS` and `TOTALJOBS` are left unset
+            // rather than flagging allocations that aren't there.
+            if !shift.job.is_empty() {
+                detail.set("DESC", &shift.job);
+            }
+
+            import.detail.push(detail);
+        }
+    }
+
+    Ok(import)
+}
+
+/// Writes the import sheets, then the optional summary sheet after them.
+pub fn write(
+    workbook: Workbook,
+    import: &SageImport,
+    summary: Option<&Summary>,
+) -> Result<(), ExcelWriteError> {
+    write_sheet(&workbook, "Timecard_Header", TIMECARD_HEADER_HEADERS, &import.header)?;
+    write_sheet(&workbook, "Timecard_Detail", TIMECARD_DETAIL_HEADERS, &import.detail)?;
+    if let Some(summary) = summary {
+        write_summary(&workbook, summary)?;
+    }
+
+    workbook.close().map_err(ExcelWriteError::Xlsx)?;
+
+    Ok(())
+}
+
+/// Writes one sheet of an import as CSV, headings first, for the header and detail CSV
+/// files Sage can import instead of a workbook.
+pub fn write_csv<W: io::Write>(
+    headers: &[&str],
+    rows: &[SageRow],
+    writer: W,
+) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(headers)?;
+    for row in rows.iter() {
+        writer.write_record(row.cells().map(|(_, value)| value.unwrap_or_default()))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_sheet(
+    workbook: &Workbook,
+    name: &str,
+    headers: &[&str],
+    rows: &[SageRow],
+) -> Result<(), ExcelWriteError> {
+    let mut sheet = workbook
+        .add_worksheet(Some(name))
+        .map_err(ExcelWriteError::Xlsx)?;
+    for (i, heading) in headers.iter().enumerate() {
+        sheet
+            .write_string(0, i as u16, heading, None)
             .map_err(ExcelWriteError::Xlsx)?;
     }
-    let formula = format!(
-            "=Timecard_Header!${}${}:${}${}",
-            to_column_letter(0),
-            1,
-            to_column_letter((TIMECARD_HEADER_HEADERS.len() - 1).try_into().unwrap()),
-            employees.len() + 1,
-    );
-    trace!("define_name `Timecard_Header` with formula: `{}`", formula);
-    workbook.define_name("Timecard_Header", &formula).map_err(ExcelWriteError::Xlsx)?;
 
-    let mut row = 1;
-    for employee in employees {
-        // Timecard_Detail
-        
-        for i in 0..employee
-            .hours
-            .clone()
-            .into_iter()
-            .filter(|shift| shift.sum_of_shift() > 0.0).count() {
-            let shift = employee.hours[i];
-
-            // A
-            sheet_detail
-                .write_string(row, 0, &employee.id, None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // B
-            sheet_detail
-                .write_string(row, 1, &end_formatted, None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // C
-            sheet_detail
-                .write_string(row, 2, payperiod, None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // D
-            sheet_detail
-                .write_string(row, 3, &format!("{}", (i + 1) * 1000), None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // E
-            sheet_detail
-                .write_string(row, 4, "2", None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // E
-            sheet_detail
-                .write_string(row, 5, "HRLY", None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // F
-            sheet_detail
-                .write_string(row, 5, "HRLY", None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // H
-            sheet_detail
-                .write_string(row, 7, &shift.date.format(DATE_FORMAT).to_string(), None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // N
-            sheet_detail
-                .write_string(row, 13, &format!("{}", shift.sum_of_shift()), None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // T
-            sheet_detail
-                .write_string(row, 19, &employee.exp_account, None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // V
-            sheet_detail
-                .write_string(row, 21, &employee.exp_account, None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // Y
-            sheet_detail
-                .write_string(row, 24, &employee.overtime_schedule, None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // AV
-            sheet_detail
-                .write_string(row, 47, "1", None)
-                .map_err(ExcelWriteError::Xlsx)?;
-            // BB
-            sheet_detail
-                .write_string(row, 53, &employee.dist_code, None)
-                .map_err(ExcelWriteError::Xlsx)?;
-
-            row += 1;
+    for (i, row) in rows.iter().enumerate() {
+        for (col, (_, value)) in row.cells().enumerate() {
+            if let Some(value) = value {
+                sheet
+                    .write_string(i as u32 + 1, col as u16, value, None)
+                    .map_err(ExcelWriteError::Xlsx)?;
+            }
         }
     }
 
     let formula = format!(
-            "=Timecard_Detail!${}${}:${}${}",
-            to_column_letter(0),
-            1,
-            to_column_letter((TIMECARD_DETAIL_HEADERS.len() - 1).try_into().unwrap()),
-            row,
+        "={}!${}${}:${}${}",
+        name,
+        to_column_letter(0),
+        1,
+        to_column_letter((headers.len() - 1).try_into().unwrap()),
+        rows.len() + 1,
     );
-    trace!("define_name `Timecard_Detail` with formula: `{}`", formula);
-    workbook.define_name("Timecard_Detail", &formula).map_err(ExcelWriteError::Xlsx)?;
-
-    workbook.close().map_err(ExcelWriteError::Xlsx)?;
+    trace!("define_name `{}` with formula: `{}`", name, formula);
+    workbook
+        .define_name(name, &formula)
+        .map_err(ExcelWriteError::Xlsx)?;
 
     Ok(())
 }
+
+#[test]
+fn it_writes_import_sheets_as_csv() {
+    use chrono::{Duration, TimeZone, Utc};
+
+    let start = Utc.ymd(2021, 5, 3);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(6));
+    let mut employee = Employee::new("1001".to_string());
+    employee.timecard = "W1".to_string();
+    for day in 0..2 {
+        let date = start + Duration::days(day as i64);
+        let mut shift = crate::employees::Shift::new(4 + day, Duration::hours(8), date);
+        shift.job = "A-100".to_string();
+        employee.hours.push(shift);
+    }
+    let import = build("W1", vec![employee], &date_range).unwrap();
+    assert_eq!(import.header[0].get("TOTALJOBS"), None);
+    assert_eq!(import.detail[0].get("DESC"), Some("A-100"));
+    for column in ["SWALLOCJOB", "JOBS", "JOBHOURS"] {
+        assert_eq!(import.detail[0].get(column), None);
+    }
+
+    let mut csv = Vec::new();
+    write_csv(TIMECARD_DETAIL_HEADERS, &import.detail, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], TIMECARD_DETAIL_HEADERS.join(","));
+    assert!(lines[1].starts_with("1001,2021-05-09,W1,"));
+}
+
+#[test]
+fn it_reads_rows_by_heading() {
+    for headers in [TIMECARD_HEADER_HEADERS, TIMECARD_DETAIL_HEADERS] {
+        let mut sorted = headers.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), headers.len());
+    }
+    let sick = TIMECARD_HEADER_HEADERS
+        .iter()
+        .position(|h| *h == "CSICKHRSP")
+        .unwrap();
+    assert_eq!(TIMECARD_HEADER_HEADERS[sick + 1], "CSICKHRSA");
+
+    let strings = |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    // Imports written before the sick hours heading was corrected repeat `CSICKHRSP`,
+    // and spreadsheets often gain blank or stray columns.
+    let headings = strings(&["EMPLOYEE", "CSICKHRSP", "CSICKHRSP", "", "NOTES"]);
+    let row = SageRow::from_headings(
+        TIMECARD_HEADER_HEADERS,
+        &headings,
+        strings(&["1001", "4", "", "x", ""]),
+    )
+    .unwrap();
+    assert_eq!(row.get("EMPLOYEE"), Some("1001"));
+    assert_eq!(row.get("CSICKHRSP"), Some("4"));
+    assert_eq!(row.get("CSICKHRSA"), None);
+
+    assert!(SageRow::from_headings(
+        TIMECARD_HEADER_HEADERS,
+        &headings,
+        strings(&["1001", "4", "2", "", ""]),
+    )
+    .is_err());
+    assert!(SageRow::from_headings(
+        TIMECARD_HEADER_HEADERS,
+        &headings,
+        strings(&["1001", "", "", "", "late"]),
+    )
+    .is_err());
+}
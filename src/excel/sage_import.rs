@@ -0,0 +1,349 @@
+use std::path::Path;
+
+use calamine::{Reader, Sheets};
+use chrono::{Date, Duration, NaiveDate, NaiveTime, Utc};
+
+use super::records::cell_to_string;
+use super::sage::{SageImport, SageRow, TIMECARD_DETAIL_HEADERS, TIMECARD_HEADER_HEADERS};
+use super::timecards::DateColumnRange;
+use super::ExcelError;
+use crate::accruals::{Accrual, Bank, PayOrAccrue};
+use crate::employees::{Employee, Shift};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMATS: &[&str] = &["%H:%M:%S", "%H:%M"];
+
+/// Whether a workbook is a Sage timecard import (e.g. one this tool generated) rather than
+/// a timecard to convert.
+pub fn is_sage_import(workbook: &Sheets) -> bool {
+    workbook
+        .sheet_names()
+        .iter()
+        .any(|name| name == "Timecard_Header")
+}
+
+/// Reads the Timecard_Header and Timecard_Detail sheets of a Sage import workbook, matching
+/// columns by their heading.
+pub fn read_sage_import(path: &Path) -> Result<SageImport, ExcelError> {
+    let mut workbook = calamine::open_workbook_auto(path).map_err(ExcelError::from)?;
+
+    Ok(SageImport {
+        header: read_sheet(&mut workbook, "Timecard_Header", TIMECARD_HEADER_HEADERS)?,
+        detail: read_sheet(&mut workbook, "Timecard_Detail", TIMECARD_DETAIL_HEADERS)?,
+    })
+}
+
+fn read_sheet(
+    workbook: &mut Sheets,
+    sheet: &str,
+    headers: &'static [&'static str],
+) -> Result<Vec<SageRow>, ExcelError> {
+    let range = workbook
+        .worksheet_range(sheet)
+        .ok_or_else(|| ExcelError::WorksheetNotFound(sheet.to_string()))?
+        .map_err(ExcelError::from)?;
+
+    let mut rows = range
+        .rows()
+        .map(|row| row.iter().map(cell_to_string).collect::<Vec<String>>());
+    let headings = match rows.next() {
+        Some(headings) => headings,
+        None => return Ok(Vec::new()),
+    };
+
+    rows.filter(|row| row.iter().any(|cell| !cell.trim().is_empty()))
+        .map(|row| {
+            SageRow::from_headings(headers, &headings, row)
+                .map_err(|message| ExcelError::Msg(format!("{}: {}", sheet, message)))
+        })
+        .collect()
+}
+
+fn required<'a>(row: &'a SageRow, heading: &str) -> Result<&'a str, ExcelError> {
+    row.get(heading)
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| ExcelError::Msg(format!("a timecard line has no `{}`", heading)))
+}
+
+fn text(row: &SageRow, heading: &str) -> String {
+    row.get(heading).unwrap_or_default().to_string()
+}
+
+fn number(row: &SageRow, heading: &str) -> Result<Option<f64>, ExcelError> {
+    match row
+        .get(heading)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(value) => value.parse::<f64>().map(Some).map_err(|_| {
+            ExcelError::Msg(format!(
+                "`{}` is not a valid number for column `{}`",
+                value, heading
+            ))
+        }),
+        None => Ok(None),
+    }
+}
+
+fn date(row: &SageRow, heading: &str) -> Result<Option<Date<Utc>>, ExcelError> {
+    match row
+        .get(heading)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(value) => NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .map(|naive| Some(Date::<Utc>::from_utc(naive, Utc)))
+            .map_err(|_| {
+                ExcelError::Msg(format!(
+                    "`{}` is not a valid date for column `{}`, expected the format YYYY-MM-DD",
+                    value, heading
+                ))
+            }),
+        None => Ok(None),
+    }
+}
+
+fn time(row: &SageRow, heading: &str) -> Result<Option<NaiveTime>, ExcelError> {
+    let value = match row
+        .get(heading)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(value, format).ok())
+        .map(Some)
+        .ok_or_else(|| {
+            ExcelError::Msg(format!(
+                "`{}` is not a valid time for column `{}`",
+                value, heading
+            ))
+        })
+}
+
+/// The bank a detail line with `PAYORACCR` counts against. Lines don't name their bank, so
+/// it is the one the employee's header has hours for, or, when there are several, the one
+/// named in the earning code (e.g. `VACP` or `COMPA`).
+fn accrual(header: &SageRow, detail: &SageRow) -> Result<Option<Accrual>, ExcelError> {
+    let code = match detail
+        .get("PAYORACCR")
+        .filter(|code| !code.trim().is_empty())
+    {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+    let action = PayOrAccrue::from_code(code).ok_or_else(|| {
+        ExcelError::Msg(format!(
+            "`{}` is not a valid value for column `PAYORACCR`",
+            code
+        ))
+    })?;
+
+    let earning = text(detail, "EARNDED").to_uppercase();
+    let banks: Vec<Bank> = Bank::ALL
+        .iter()
+        .copied()
+        .filter(|bank| {
+            let column = format!("C{}HRS{}", bank.column(), action.suffix());
+            number(header, &column).ok().flatten().unwrap_or_default() != 0.0
+        })
+        .collect();
+    let bank = match banks.as_slice() {
+        [bank] => Some(*bank),
+        banks => banks
+            .iter()
+            .copied()
+            .find(|bank| earning.contains(bank.column())),
+    };
+
+    bank.map(|bank| Some(Accrual { bank, action })).ok_or_else(|| {
+        ExcelError::Msg(format!(
+            "employee `{}` has a `{}` line paid from or accrued to a bank, but it can't be told which",
+            text(detail, "EMPLOYEE"),
+            earning
+        ))
+    })
+}
+
+/// The employees and hours of a Sage timecard import, read back into the same model the
+/// timecard parser produces so they can be checked, compared or exported again.
+#[derive(Clone)]
+pub struct SageTimecard {
+    pub timecard: String,
+    pub start: Date<Utc>,
+    pub end: Date<Utc>,
+    pub employees: Vec<Employee>,
+}
+
+impl SageTimecard {
+    pub fn from_import(import: &SageImport) -> Result<Self, ExcelError> {
+        let first = import
+            .header
+            .first()
+            .ok_or_else(|| ExcelError::Msg("the Sage import has no employees".to_string()))?;
+        let timecard = text(first, "TIMECARD");
+        let end = date(first, "PEREND")?
+            .ok_or_else(|| ExcelError::Msg("a timecard line has no `PEREND`".to_string()))?;
+
+        let mut employees = Vec::new();
+        for row in import.header.iter() {
+            let mut employee = Employee::new(required(row, "EMPLOYEE")?.to_string());
+            employee.last_name = text(row, "LASTNAME");
+            employee.first_name = text(row, "FIRSTNAME");
+            employee.hire_date = date(row, "HIREDATE")?;
+            employee.fire_date = date(row, "FIREDATE")?;
+            employee.pay_frequency = text(row, "PAYFREQ");
+            employee.part_time = text(row, "PARTTIME");
+            employee.shift_schedule = text(row, "SHIFTSCHED");
+            employee.status = text(row, "STATUS");
+            employee.inactive_date = date(row, "INACTDATE")?;
+            employee.timecard = text(row, "TIMECARD");
+            employee.timecard_desc = text(row, "TCARDDESC");
+            employees.push(employee);
+        }
+
+        let mut start = end;
+        for row in import.detail.iter() {
+            let id = required(row, "EMPLOYEE")?;
+            let n = employees.iter().position(|e| e.id == id).ok_or_else(|| {
+                ExcelError::Msg(format!(
+                    "employee `{}` has detail lines but no header line",
+                    id
+                ))
+            })?;
+            let employee = &mut employees[n];
+
+            employee.exp_account = text(row, "EXPACCT");
+            employee.liab_account = text(row, "LIABACCT");
+            employee.ot_account = text(row, "OTACCT");
+            employee.shift_account = text(row, "SHIFTACCT");
+            employee.overtime_schedule = text(row, "OTSCHED");
+            employee.dist_code = text(row, "DISTCODE");
+            if row.get("RRATEOVER") == Some("1") {
+                employee.override_rate = true;
+                employee.rate = number(row, "DEFRRATE")?;
+            }
+
+            let shift_date = date(row, "EARDEDDATE")?.ok_or_else(|| {
+                ExcelError::Msg(format!("employee `{}` has a line with no `EARDEDDATE`", id))
+            })?;
+            let hours = required(row, "HOURS")?.parse::<f64>().map_err(|_| {
+                ExcelError::Msg(format!("employee `{}` has a line with invalid `HOURS`", id))
+            })?;
+            start = start.min(shift_date);
+
+            let mut shift = Shift::new(
+                0,
+                Duration::minutes((hours * 60.0).round() as i64),
+                shift_date,
+            );
+            shift.earning = text(row, "EARNDED");
+            shift.shift_number = text(row, "SHIFTNUM");
+            shift.start = time(row, "STARTTIME")?;
+            shift.stop = time(row, "STOPTIME")?;
+            shift.accrual = accrual(&import.header[n], row)?;
+            if row.get("RRATEOVER") == Some("1") {
+                shift.rate = number(row, "RATE")?.filter(|rate| Some(*rate) != employee.rate);
+            }
+            shift.job = text(row, "DESC");
+            for (n, segment) in shift.segments.iter_mut().enumerate() {
+                segment.value = text(row, &format!("GLSEG{}", n + 1));
+                segment.id = text(row, &format!("GLSEGID{}", n + 1));
+                segment.desc = text(row, &format!("GLSEGDESC{}", n + 1));
+            }
+            employee.hours.push(shift);
+        }
+
+        // Shifts are placed as though the timecard's dates started in the first column.
+        for employee in employees.iter_mut() {
+            for shift in employee.hours.iter_mut() {
+                shift.col = (shift.date - start).num_days() as usize;
+            }
+        }
+
+        Ok(Self {
+            timecard,
+            start,
+            end,
+            employees,
+        })
+    }
+
+    /// The pay period as a date range, for passing the employees back through the pipeline.
+    pub fn date_range(&self) -> DateColumnRange {
+        let mut date_range = DateColumnRange::new();
+        date_range.head = 0;
+        date_range.tail = (self.end - self.start).num_days() as usize;
+        date_range.start = Some(self.start);
+        date_range.end = Some(self.end);
+        date_range
+    }
+}
+
+#[test]
+fn it_round_trips_generated_imports() {
+    use crate::excel::sage::build;
+
+    let start = Date::<Utc>::from_utc(NaiveDate::from_ymd(2021, 5, 2), Utc);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(6));
+
+    let mut employee = Employee::new("1001".to_string());
+    employee.last_name = "Doe".to_string();
+    employee.hire_date = Some(start - Duration::days(400));
+    employee.exp_account = "6000-100".to_string();
+    employee.dist_code = "SHOP".to_string();
+    employee.rate = Some(24.5);
+    employee.override_rate = true;
+    let mut night = Shift::new(5, Duration::minutes(254), start + Duration::days(1));
+    night.start = NaiveTime::from_hms_opt(22, 0, 0);
+    night.stop = NaiveTime::from_hms_opt(2, 14, 0);
+    night.shift_number = "2".to_string();
+    night.job = "A-100".to_string();
+    night.segments[0].value = "200".to_string();
+    night.rate = Some(27.25);
+    employee.hours.push(night);
+    employee
+        .hours
+        .push(Shift::new(6, Duration::hours(8), start + Duration::days(2)));
+    let mut banked = Shift::new(7, Duration::hours(3), start + Duration::days(3));
+    banked.earning = "COMPA".to_string();
+    banked.accrual = Some(Accrual {
+        bank: Bank::Comp,
+        action: PayOrAccrue::Accrue,
+    });
+    employee.hours.push(banked);
+    let mut vacation = Shift::new(8, Duration::hours(8), start + Duration::days(4));
+    vacation.earning = "VAC".to_string();
+    vacation.accrual = Some(Accrual {
+        bank: Bank::Vacation,
+        action: PayOrAccrue::Pay,
+    });
+    employee.hours.push(vacation);
+
+    let import = build("SEMI", vec![employee], &date_range).unwrap();
+    let timecard = SageTimecard::from_import(&import).unwrap();
+    assert_eq!(timecard.timecard, "SEMI");
+    assert_eq!(timecard.employees[0].hours[0].job, "A-100");
+    assert_eq!(timecard.employees[0].hours[0].rate, Some(27.25));
+    assert_eq!(import.detail[1].get("RATE"), Some("24.5"));
+    let accruals: Vec<Option<Accrual>> = timecard.employees[0]
+        .hours
+        .iter()
+        .map(|shift| shift.accrual)
+        .collect();
+    assert_eq!(accruals.len(), 4);
+    assert_eq!(accruals[2].unwrap().bank, Bank::Comp);
+    assert_eq!(accruals[2].unwrap().action, PayOrAccrue::Accrue);
+    assert_eq!(accruals[3].unwrap().bank, Bank::Vacation);
+
+    let again = build("SEMI", timecard.employees.clone(), &timecard.date_range()).unwrap();
+    assert_eq!(import, again);
+}
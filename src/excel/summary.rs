@@ -0,0 +1,127 @@
+use xlsxwriter::{FormatBorder, FormatColor, FormatPatterns, Workbook};
+
+use crate::excel::sage::ExcelWriteError;
+use crate::summary::Summary;
+
+const SHEET_NAME: &str = "Summary";
+const DAY_FORMAT: &str = "%a %m/%d";
+const HOURS_FORMAT: &str = "0.00";
+
+/// Adds a sheet for managers to sign off on, with each employee's hours by day and their
+/// regular, overtime and total hours.
+///
+/// The sheet has no defined name, so Sage skips it when importing the workbook.
+pub fn write_summary(workbook: &Workbook, summary: &Summary) -> Result<(), ExcelWriteError> {
+    let heading = workbook
+        .add_format()
+        .set_bold()
+        .set_pattern(FormatPatterns::Solid)
+        .set_bg_color(FormatColor::Silver)
+        .set_border_bottom(FormatBorder::Thin);
+    let hours = workbook.add_format().set_num_format(HOURS_FORMAT);
+    let total_label = workbook
+        .add_format()
+        .set_bold()
+        .set_border_top(FormatBorder::Thin);
+    let total_hours = workbook
+        .add_format()
+        .set_bold()
+        .set_num_format(HOURS_FORMAT)
+        .set_border_top(FormatBorder::Thin);
+
+    let mut sheet = workbook
+        .add_worksheet(Some(SHEET_NAME))
+        .map_err(ExcelWriteError::Xlsx)?;
+
+    let mut headings = vec![
+        "Employee".to_string(),
+        "Name".to_string(),
+        "Dist Code".to_string(),
+        "Exp Account".to_string(),
+        "OT Account".to_string(),
+    ];
+    let first_day = headings.len() as u16;
+    headings.extend(
+        summary
+            .days
+            .iter()
+            .map(|day| day.format(DAY_FORMAT).to_string()),
+    );
+    let totals = headings.len() as u16;
+    headings.extend(
+        ["Regular", "Overtime", "Total"]
+            .iter()
+            .map(|h| h.to_string()),
+    );
+
+    for (col, text) in headings.iter().enumerate() {
+        sheet
+            .write_string(0, col as u16, text, Some(&heading))
+            .map_err(ExcelWriteError::Xlsx)?;
+    }
+    sheet
+        .set_column(1, 1, 24.0, None)
+        .map_err(ExcelWriteError::Xlsx)?;
+    sheet
+        .set_column(2, first_day - 1, 12.0, None)
+        .map_err(ExcelWriteError::Xlsx)?;
+    sheet
+        .set_column(first_day, totals + 2, 10.0, None)
+        .map_err(ExcelWriteError::Xlsx)?;
+    sheet.freeze_panes(1, 2);
+
+    for (i, row) in summary.rows.iter().enumerate() {
+        let r = i as u32 + 1;
+        for (col, text) in [
+            &row.id,
+            &row.name,
+            &row.dist_code,
+            &row.exp_account,
+            &row.ot_account,
+        ]
+        .iter()
+        .enumerate()
+        {
+            sheet
+                .write_string(r, col as u16, text, None)
+                .map_err(ExcelWriteError::Xlsx)?;
+        }
+        for (n, value) in row.daily.iter().enumerate() {
+            if *value > 0.0 {
+                sheet
+                    .write_number(r, first_day + n as u16, *value as f64, Some(&hours))
+                    .map_err(ExcelWriteError::Xlsx)?;
+            }
+        }
+        for (n, value) in [row.regular, row.overtime, row.total()].iter().enumerate() {
+            sheet
+                .write_number(r, totals + n as u16, *value as f64, Some(&hours))
+                .map_err(ExcelWriteError::Xlsx)?;
+        }
+    }
+
+    let r = summary.rows.len() as u32 + 1;
+    sheet
+        .write_string(r, 0, "Total", Some(&total_label))
+        .map_err(ExcelWriteError::Xlsx)?;
+    for col in 1..first_day {
+        sheet
+            .write_blank(r, col, Some(&total_label))
+            .map_err(ExcelWriteError::Xlsx)?;
+    }
+    for (n, value) in summary.daily_totals().iter().enumerate() {
+        sheet
+            .write_number(r, first_day + n as u16, *value as f64, Some(&total_hours))
+            .map_err(ExcelWriteError::Xlsx)?;
+    }
+    for (n, value) in [summary.regular(), summary.overtime(), summary.total()]
+        .iter()
+        .enumerate()
+    {
+        sheet
+            .write_number(r, totals + n as u16, *value as f64, Some(&total_hours))
+            .map_err(ExcelWriteError::Xlsx)?;
+    }
+
+    Ok(())
+}
@@ -1,15 +1,84 @@
-use super::ExcelError;
+use super::{date_from_serial, ExcelError};
 use crate::employees::{Employee, Shift};
 use calamine::{DataType, Reader, Sheets};
-use chrono::{Date, Duration, NaiveDate, Utc};
+use chrono::{Date, Duration, NaiveTime, Utc};
 use std::collections::HashMap;
 
+/// Optional columns of the timecard worksheet, beyond the fixed employee id, OT schedule,
+/// dist code and expense account columns (`A` to `D`) and the date columns.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    /// Job or project code for the row. Employees working several jobs get one row per job.
+    /// It is only written to the detail line's description, not allocated in Sage.
+    pub job: Option<usize>,
+    /// GL segment overrides for the row, written to `GLSEG1` to `GLSEG3`.
+    pub segments: [Option<usize>; 3],
+    /// Earning code for the row's hours, so one day can be split across rows paid under
+    /// different earnings.
+    pub earning: Option<usize>,
+    /// Hourly rate for the row's hours, for employees paid differently on different jobs.
+    pub rate: Option<usize>,
+}
+
+/// A value read from one of the optional [`Layout`] columns, applied to every shift on its row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RowField {
+    Job,
+    Segment(usize),
+    Earning,
+    Rate,
+}
+
+impl Layout {
+    fn field(&self, col: usize) -> Option<RowField> {
+        if self.job == Some(col) {
+            return Some(RowField::Job);
+        }
+        if self.earning == Some(col) {
+            return Some(RowField::Earning);
+        }
+        if self.rate == Some(col) {
+            return Some(RowField::Rate);
+        }
+
+        self.segments
+            .iter()
+            .position(|segment| *segment == Some(col))
+            .map(RowField::Segment)
+    }
+}
+
+impl RowField {
+    fn apply(&self, shift: &mut Shift, value: &str) -> Result<(), String> {
+        match self {
+            RowField::Job => shift.job = value.to_string(),
+            RowField::Segment(n) => shift.segments[*n].value = value.to_string(),
+            RowField::Earning if !value.is_empty() => shift.earning = value.to_uppercase(),
+            RowField::Earning => {}
+            RowField::Rate if !value.is_empty() => {
+                let rate = value
+                    .trim_start_matches('$')
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|rate| *rate >= 0.0)
+                    .ok_or_else(|| format!("`{}` is not a valid rate", value))?;
+                shift.rate = Some(rate);
+            }
+            RowField::Rate => {}
+        }
+
+        Ok(())
+    }
+}
+
 pub fn parse_worksheet<'a>(
     workbook: &'a mut Sheets,
     sheet: &'a str,
     date_range: DateColumnRange,
+    layout: &Layout,
 ) -> Result<Vec<Employee>, ExcelError> {
     let mut map: HashMap<usize, Employee> = HashMap::new();
+    let mut fields: HashMap<usize, Vec<(RowField, String)>> = HashMap::new();
 
     // Read whole worksheet data and provide some statistics
     if let Some(Ok(range)) = workbook.worksheet_range(sheet) {
@@ -19,8 +88,20 @@ pub fn parse_worksheet<'a>(
                     if col == 0 && !map.contains_key(&row) {
                         map.insert(row, Employee::new(txt.clone()));
                     }
+                    if let Some(field) = layout.field(col) {
+                        fields
+                            .entry(row)
+                            .or_default()
+                            .push((field, txt.trim().to_string()));
+                    }
 
                     if let Some(e) = map.get_mut(&row) {
+                        if date_range.in_range(col) {
+                            if let Some(shift) = parse_shift_cell(txt, col, &date_range) {
+                                e.hours.push(shift);
+                            }
+                        }
+
                         match col {
                             1 => e.overtime_schedule = txt.clone(),
                             2 => e.dist_code = txt.clone(),
@@ -30,6 +111,10 @@ pub fn parse_worksheet<'a>(
                     }
                 }
                 DataType::Int(i) => {
+                    if let Some(field) = layout.field(col) {
+                        fields.entry(row).or_default().push((field, i.to_string()));
+                    }
+
                     if let Some(e) = map.get_mut(&row) {
                         match col {
                             1 => e.overtime_schedule = i.to_string(),
@@ -40,13 +125,23 @@ pub fn parse_worksheet<'a>(
                     }
                 }
                 DataType::Float(f) => {
+                    if let Some(field) = layout.field(col) {
+                        // Rates keep their cents, codes typed as numbers are whole.
+                        let value = if field == RowField::Rate {
+                            f.to_string()
+                        } else {
+                            f.round().to_string()
+                        };
+                        fields.entry(row).or_default().push((field, value));
+                    }
+
                     if let Some(e) = map.get_mut(&row) {
                         if date_range.in_range(col) && f > &0.0 {
-                            e.hours.push(Shift {
+                            e.hours.push(Shift::new(
                                 col,
-                                duration: Duration::hours((24.0 * *f) as i64),
-                                date: date_range.date_from_column(col).unwrap(),
-                            })
+                                Duration::hours((24.0 * *f) as i64),
+                                date_range.date_from_column(col).unwrap(),
+                            ))
                         }
 
                         match col {
@@ -61,12 +156,93 @@ pub fn parse_worksheet<'a>(
             }
         }
 
-        Ok(map.values().cloned().collect())
+        for (row, values) in fields {
+            if let Some(e) = map.get_mut(&row) {
+                for shift in e.hours.iter_mut() {
+                    for (field, value) in values.iter() {
+                        field.apply(shift, value).map_err(|message| {
+                            ExcelError::Msg(format!("row {} of `{}`: {}", row + 1, sheet, message))
+                        })?;
+                    }
+                }
+            }
+        }
+
+        let mut rows: Vec<(usize, Employee)> = map.into_iter().collect();
+        rows.sort_by_key(|(row, _)| *row);
+
+        Ok(merge_rows(rows.into_iter().map(|(_, e)| e).collect()))
     } else {
         Err(ExcelError::WorksheetNotFound(sheet.to_string()))
     }
 }
 
+/// Parses a day written as text: either clock times (`22:00-06:30`, which may run past
+/// midnight) or hours followed by a shift code (`8N`, `7.5 E`).
+fn parse_shift_cell(txt: &str, col: usize, date_range: &DateColumnRange) -> Option<Shift> {
+    let txt = txt.trim();
+    let date = date_range.date_from_column(col)?;
+
+    if let Some((start, stop)) = txt.split_once('-') {
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let stop = NaiveTime::parse_from_str(stop.trim(), "%H:%M").ok()?;
+        let mut duration = stop - start;
+        if duration <= Duration::zero() {
+            duration = duration + Duration::days(1);
+        }
+
+        let mut shift = Shift::new(col, duration, date);
+        shift.start = Some(start);
+        shift.stop = Some(stop);
+        return Some(shift);
+    }
+
+    let split = txt
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(txt.len());
+    let (hours, code) = txt.split_at(split);
+    let hours = hours.parse::<f64>().ok().filter(|hours| *hours > 0.0)?;
+
+    let mut shift = Shift::new(col, Duration::minutes((hours * 60.0).round() as i64), date);
+    shift.shift_code = code.trim().to_string();
+    Some(shift)
+}
+
+/// Combines rows that share an employee id (e.g. one row per job) into a single employee,
+/// keeping the first OT schedule, dist code and expense account that isn't blank.
+fn merge_rows(rows: Vec<Employee>) -> Vec<Employee> {
+    let mut employees: Vec<Employee> = Vec::new();
+
+    for row in rows {
+        let employee = match employees.iter_mut().find(|e| e.id == row.id) {
+            Some(employee) => employee,
+            None => {
+                employees.push(row);
+                continue;
+            }
+        };
+
+        if employee.overtime_schedule.is_empty() {
+            employee.overtime_schedule = row.overtime_schedule;
+        }
+        if employee.dist_code.is_empty() {
+            employee.dist_code = row.dist_code;
+        }
+        if employee.exp_account.is_empty() {
+            employee.exp_account = row.exp_account;
+        }
+        employee.hours.extend(row.hours);
+    }
+
+    for employee in employees.iter_mut() {
+        employee
+            .hours
+            .sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.job.cmp(&b.job)));
+    }
+
+    employees
+}
+
 pub fn parse_date_range<'a>(
     workbook: &'a mut Sheets,
     sheet: &'a str,
@@ -74,33 +250,16 @@ pub fn parse_date_range<'a>(
     let result = workbook
         .worksheet_range(sheet)
         .ok_or_else(|| ExcelError::WorksheetNotFound(sheet.to_string()))?;
-    let range = match result {
-        Ok(r) => r,
-        Err(e) => match e {
-            calamine::Error::Io(err) => {
-                return Err(ExcelError::Io(err));
-            }
-            calamine::Error::Xlsx(err) => {
-                return Err(ExcelError::Xlsx(err));
-            }
-            calamine::Error::Msg(msg) => {
-                return Err(ExcelError::Msg(msg.to_string()));
-            }
-            _ => {
-                return Err(ExcelError::Unexpected);
-            }
-        },
-    };
+    let range = result.map_err(ExcelError::from)?;
 
     // Read whole worksheet data and provide some statistics
     let mut date_range = DateColumnRange::new();
     for (row, col, cell) in range.cells() {
-        let date = Date::<Utc>::from_utc(NaiveDate::from_ymd(1900, 1, 1), Utc);
         if let DataType::Float(f) = cell {
             // The date using the number of days since Jan 1, 1900 as provided.
             // You might be wondering why excel would do such a thing?
             // Too bad!
-            let parsed_date = date + Duration::days((f.round() - 2.0) as i64);
+            let parsed_date = date_from_serial(*f);
             // Set date row to first row encountered with dates
             if date_range.row == None {
                 date_range.row = Some(row);
@@ -140,6 +299,11 @@ impl DateColumnRange {
         }
     }
 
+    /// The worksheet row the dates were found on.
+    pub fn row(&self) -> Option<usize> {
+        self.row
+    }
+
     pub fn range(&self) -> Option<(Date<Utc>, Date<Utc>)> {
         if self.start.is_none() || self.end.is_none() {
             return None;
@@ -190,3 +354,51 @@ impl Iterator for DateColumnRange {
         date
     }
 }
+
+#[test]
+fn it_merges_rows_per_job() {
+    use chrono::TimeZone;
+
+    let today = Utc.ymd(2021, 5, 3);
+    let row = |job: &str, dist_code: &str, days: i64| {
+        let mut e = Employee::new("1001".to_string());
+        e.dist_code = dist_code.to_string();
+        let mut shift = Shift::new(4, Duration::hours(4), today + Duration::days(days));
+        shift.job = job.to_string();
+        e.hours.push(shift);
+        e
+    };
+
+    let employees = merge_rows(vec![
+        row("B-200", "", 1),
+        row("A-100", "SHOP", 0),
+        row("A-100", "", 1),
+    ]);
+
+    assert_eq!(employees.len(), 1);
+    assert_eq!(employees[0].dist_code, "SHOP");
+    let jobs: Vec<&str> = employees[0].hours.iter().map(|s| s.job.as_str()).collect();
+    assert_eq!(jobs, vec!["A-100", "A-100", "B-200"]);
+}
+
+#[test]
+fn it_parses_shift_cells() {
+    use chrono::TimeZone;
+
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(Utc.ymd(2021, 5, 3));
+    date_range.end = Some(Utc.ymd(2021, 5, 3) + Duration::days(6));
+
+    let night = parse_shift_cell("22:00-06:30", 5, &date_range).unwrap();
+    assert_eq!(night.sum_of_shift(), 8.5);
+    assert_eq!(night.date, Utc.ymd(2021, 5, 3) + Duration::days(1));
+    assert_eq!(night.start, NaiveTime::from_hms_opt(22, 0, 0));
+
+    let coded = parse_shift_cell("7.5 E", 4, &date_range).unwrap();
+    assert_eq!(coded.sum_of_shift(), 7.5);
+    assert_eq!(coded.shift_code, "E");
+
+    assert!(parse_shift_cell("OFF", 4, &date_range).is_none());
+}
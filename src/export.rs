@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::path::Path;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::employees::{sum_of_hours, Employee, Shift};
+use crate::excel::timecards::DateColumnRange;
+use crate::excel::to_column_letter;
+use crate::validation::Issue;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M";
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("the timecard has no date range")]
+    MissingDates,
+    #[error("io error")]
+    Io(std::io::Error),
+    #[error("json error")]
+    Json(serde_json::Error),
+    #[error("yaml error")]
+    Yaml(serde_yaml::Error),
+}
+
+/// The normalized pay period: what was parsed from the timecard after every lookup file
+/// was applied, along with the issues found while checking it.
+#[derive(Debug, Clone, Serialize)]
+pub struct PayPeriod {
+    pub sheet: String,
+    pub dates: PeriodDates,
+    pub employees: Vec<EmployeeHours>,
+    pub total_hours: f32,
+    pub issues: Vec<IssueEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodDates {
+    pub start: String,
+    pub end: String,
+    /// Column letters of the first and last date on the timecard.
+    pub first_column: String,
+    pub last_column: String,
+    pub days: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmployeeHours {
+    pub id: String,
+    pub last_name: String,
+    pub first_name: String,
+    pub dist_code: String,
+    pub overtime_schedule: String,
+    pub exp_account: String,
+    pub ot_account: String,
+    pub liab_account: String,
+    pub shift_account: String,
+    pub total_hours: f32,
+    pub shifts: Vec<ShiftHours>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShiftHours {
+    pub date: String,
+    pub hours: f32,
+    pub earning: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub job: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<String>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub shift_number: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueEntry {
+    pub severity: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub employee: Option<String>,
+    pub message: String,
+}
+
+impl PayPeriod {
+    pub fn new(
+        sheet: &str,
+        date_range: &DateColumnRange,
+        employees: &[Employee],
+        issues: &[Issue],
+    ) -> Result<Self, ExportError> {
+        let (start, end) = date_range.range().ok_or(ExportError::MissingDates)?;
+        let employees: Vec<EmployeeHours> = employees.iter().map(EmployeeHours::from).collect();
+
+        Ok(Self {
+            sheet: sheet.to_string(),
+            dates: PeriodDates {
+                start: start.format(DATE_FORMAT).to_string(),
+                end: end.format(DATE_FORMAT).to_string(),
+                first_column: to_column_letter(date_range.head as i32),
+                last_column: to_column_letter(date_range.tail as i32),
+                days: date_range
+                    .clone()
+                    .map(|date| date.format(DATE_FORMAT).to_string())
+                    .collect(),
+            },
+            total_hours: employees.iter().map(|e| e.total_hours).sum(),
+            employees,
+            issues: issues.iter().map(IssueEntry::from).collect(),
+        })
+    }
+
+    /// Writes the pay period as YAML when the path ends in `.yaml` or `.yml`, and as JSON
+    /// otherwise.
+    pub fn write(&self, path: &Path) -> Result<(), ExportError> {
+        let file = File::create(path).map_err(ExportError::Io)?;
+        let is_yaml = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+            .unwrap_or(false);
+
+        if is_yaml {
+            serde_yaml::to_writer(file, self).map_err(ExportError::Yaml)
+        } else {
+            serde_json::to_writer_pretty(file, self).map_err(ExportError::Json)
+        }
+    }
+}
+
+impl From<&Issue> for IssueEntry {
+    fn from(issue: &Issue) -> Self {
+        Self {
+            severity: format!("{:?}", issue.severity).to_lowercase(),
+            employee: issue.employee.clone(),
+            message: issue.message.clone(),
+        }
+    }
+}
+
+impl From<&Employee> for EmployeeHours {
+    fn from(employee: &Employee) -> Self {
+        Self {
+            id: employee.id.clone(),
+            last_name: employee.last_name.clone(),
+            first_name: employee.first_name.clone(),
+            dist_code: employee.dist_code.clone(),
+            overtime_schedule: employee.overtime_schedule.clone(),
+            exp_account: employee.exp_account.clone(),
+            ot_account: employee.overtime_account().to_string(),
+            liab_account: employee.liab_account.clone(),
+            shift_account: employee.shift_account.clone(),
+            total_hours: sum_of_hours(employee.hours.clone()),
+            shifts: employee
+                .hours
+                .iter()
+                .map(|shift| ShiftHours {
+                    rate: employee.shift_rate(shift),
+                    ..ShiftHours::from(shift)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&Shift> for ShiftHours {
+    fn from(shift: &Shift) -> Self {
+        let format_time = |time: chrono::NaiveTime| time.format(TIME_FORMAT).to_string();
+
+        Self {
+            date: shift.date.format(DATE_FORMAT).to_string(),
+            hours: shift.sum_of_shift(),
+            earning: shift.earning.clone(),
+            job: shift.job.clone(),
+            start: shift.start.map(format_time),
+            stop: shift.stop.map(format_time),
+            shift_number: shift.shift_number.clone(),
+            segments: shift
+                .segments
+                .iter()
+                .map(|segment| segment.value.clone())
+                .filter(|value| !value.is_empty())
+                .collect(),
+            rate: shift.rate,
+        }
+    }
+}
+
+#[test]
+fn it_serializes_the_pay_period() {
+    use chrono::{Duration, TimeZone, Utc};
+
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 5;
+    date_range.start = Some(Utc.ymd(2021, 5, 3));
+    date_range.end = Some(Utc.ymd(2021, 5, 3) + Duration::days(1));
+
+    let mut employee = Employee::new("1001".to_string());
+    employee.exp_account = "6000".to_string();
+    employee
+        .hours
+        .push(Shift::new(4, Duration::hours(8), Utc.ymd(2021, 5, 3)));
+    let issues = vec![Issue::warning(Some("1001"), "no hire date".to_string())];
+
+    let period = PayPeriod::new("Week 1", &date_range, &[employee], &issues).unwrap();
+    let json = serde_json::to_value(&period).unwrap();
+
+    assert_eq!(json["dates"]["first_column"], "E");
+    assert_eq!(json["dates"]["days"].as_array().unwrap().len(), 2);
+    assert_eq!(json["total_hours"], 8.0);
+    assert_eq!(json["employees"][0]["ot_account"], "6000");
+    assert_eq!(json["employees"][0]["shifts"][0]["earning"], "HRLY");
+    assert!(json["employees"][0]["shifts"][0].get("job").is_none());
+    assert_eq!(json["issues"][0]["severity"], "warning");
+}
@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::employees::{sum_of_hours, Employee};
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("io error")]
+    Io(std::io::Error),
+    #[error("json error")]
+    Json(serde_json::Error),
+    #[error("run {0} is not in the history")]
+    UnknownRun(usize),
+}
+
+/// An employee's hours in an exported run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunEmployee {
+    pub id: String,
+    pub hours: f32,
+}
+
+/// One export of a timecard to a Sage import workbook.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    /// When the run happened, in RFC 3339.
+    pub time: String,
+    pub input: String,
+    /// SHA-256 of the timecard workbook, to recognise the same file exported again.
+    pub input_hash: String,
+    pub sheet: String,
+    pub period_end: String,
+    pub output: String,
+    /// Whether the run only wrote correction lines for an earlier run.
+    #[serde(default)]
+    pub correction: bool,
+    pub total_hours: f32,
+    pub employees: Vec<RunEmployee>,
+    /// Changes made to the timecard on the review screen before it was exported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edits: Vec<String>,
+}
+
+impl Run {
+    pub fn new(
+        input: &Path,
+        input_hash: String,
+        sheet: &str,
+        period_end: &str,
+        output: &str,
+        correction: bool,
+        employees: &[Employee],
+    ) -> Self {
+        let employees: Vec<RunEmployee> = employees
+            .iter()
+            .map(|employee| RunEmployee {
+                id: employee.id.clone(),
+                hours: sum_of_hours(employee.hours.clone()),
+            })
+            .filter(|employee| employee.hours > 0.0)
+            .collect();
+
+        Self {
+            time: chrono::Utc::now().to_rfc3339(),
+            input: input.display().to_string(),
+            input_hash,
+            sheet: sheet.to_string(),
+            period_end: period_end.to_string(),
+            output: output.to_string(),
+            correction,
+            total_hours: employees.iter().map(|employee| employee.hours).sum(),
+            employees,
+            edits: Vec::new(),
+        }
+    }
+}
+
+/// Hashes a timecard workbook for its run record.
+pub fn hash_file(path: &Path) -> Result<String, HistoryError> {
+    let contents = std::fs::read(path).map_err(HistoryError::Io)?;
+    Ok(format!("{:x}", Sha256::digest(&contents)))
+}
+
+/// A run already exported that paid some of the same employees for the same period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub run: usize,
+    pub time: String,
+    pub same_input: bool,
+    pub employees: Vec<String>,
+}
+
+/// Every export recorded in a local JSON file, so a period can't quietly be paid twice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    pub runs: Vec<Run>,
+}
+
+impl History {
+    /// Reads the history, starting an empty one when the file doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self, HistoryError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let file = File::open(path).map_err(HistoryError::Io)?;
+        serde_json::from_reader(file).map_err(HistoryError::Json)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), HistoryError> {
+        let file = File::create(path).map_err(HistoryError::Io)?;
+        serde_json::to_writer_pretty(file, self).map_err(HistoryError::Json)
+    }
+
+    /// Runs numbered from 1, as listed by the `history` subcommand.
+    pub fn get(&self, number: usize) -> Result<&Run, HistoryError> {
+        number
+            .checked_sub(1)
+            .and_then(|n| self.runs.get(n))
+            .ok_or(HistoryError::UnknownRun(number))
+    }
+
+    /// Earlier runs, other than corrections, that exported any of the same employees for
+    /// the same period end.
+    pub fn duplicates(&self, run: &Run) -> Vec<Duplicate> {
+        self.runs
+            .iter()
+            .enumerate()
+            .filter(|(_, earlier)| !earlier.correction && earlier.period_end == run.period_end)
+            .filter_map(|(n, earlier)| {
+                let employees: Vec<String> = run
+                    .employees
+                    .iter()
+                    .filter(|employee| earlier.employees.iter().any(|e| e.id == employee.id))
+                    .map(|employee| employee.id.clone())
+                    .collect();
+                if employees.is_empty() {
+                    return None;
+                }
+
+                Some(Duplicate {
+                    run: n + 1,
+                    time: earlier.time.clone(),
+                    same_input: earlier.input_hash == run.input_hash,
+                    employees,
+                })
+            })
+            .collect()
+    }
+
+    pub fn record(&mut self, run: Run) {
+        self.runs.push(run);
+    }
+}
+
+#[test]
+fn it_finds_runs_for_the_same_period() {
+    use crate::employees::Shift;
+    use chrono::{Duration, TimeZone, Utc};
+
+    let employee = |id: &str| {
+        let mut e = Employee::new(id.to_string());
+        e.hours
+            .push(Shift::new(4, Duration::hours(8), Utc.ymd(2021, 5, 3)));
+        e
+    };
+    let run = |hash: &str, period_end: &str, correction: bool, ids: &[&str]| {
+        let employees: Vec<Employee> = ids.iter().map(|id| employee(id)).collect();
+        Run::new(
+            Path::new("week.xlsx"),
+            hash.to_string(),
+            "Week 1",
+            period_end,
+            "output.xlsx",
+            correction,
+            &employees,
+        )
+    };
+
+    let mut history = History::default();
+    history.record(run("a", "2021-05-08", false, &["1001", "1002"]));
+    history.record(run("b", "2021-05-15", false, &["1001"]));
+    history.record(run("c", "2021-05-08", true, &["1003"]));
+
+    let again = run("a", "2021-05-08", false, &["1002", "1003", "1004"]);
+    let duplicates = history.duplicates(&again);
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].run, 1);
+    assert!(duplicates[0].same_input);
+    assert_eq!(duplicates[0].employees, vec!["1002"]);
+    assert_eq!(history.get(2).unwrap().total_hours, 8.0);
+    assert!(history.get(0).is_err());
+}
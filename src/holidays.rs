@@ -0,0 +1,364 @@
+use std::path::Path;
+
+use chrono::{Date, Datelike, Duration, NaiveDate, Utc, Weekday};
+
+use crate::employees::{Employee, Shift, REGULAR_EARNING};
+use crate::excel::records::{read_records, Record};
+use crate::excel::timecards::DateColumnRange;
+use crate::excel::ExcelError;
+use crate::validation::Issue;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+pub const PROVINCES: &[&str] = &["AB", "BC", "MB", "NB", "NL", "NS", "ON", "PE", "QC", "SK"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holiday {
+    pub date: Date<Utc>,
+    pub name: String,
+}
+
+/// The statutory holidays that apply to a timecard.
+#[derive(Debug, Clone, Default)]
+pub struct HolidayCalendar {
+    holidays: Vec<Holiday>,
+}
+
+fn date(year: i32, month: u32, day: u32) -> Date<Utc> {
+    Date::<Utc>::from_utc(NaiveDate::from_ymd(year, month, day), Utc)
+}
+
+/// The `n`th (1 based) `weekday` of a month, e.g. the third Monday of February.
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u32) -> Date<Utc> {
+    let first = date(year, month, 1);
+    let offset = (7 + weekday.num_days_from_monday() - first.weekday().num_days_from_monday()) % 7;
+    first + Duration::days((offset + 7 * (n - 1)) as i64)
+}
+
+/// Victoria Day is the Monday on or before May 24.
+fn victoria_day(year: i32) -> Date<Utc> {
+    let may_24 = date(year, 5, 24);
+    may_24 - Duration::days(may_24.weekday().num_days_from_monday() as i64)
+}
+
+/// Easter Sunday, using the anonymous Gregorian algorithm.
+fn easter(year: i32) -> Date<Utc> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    date(year, month as u32, day as u32)
+}
+
+impl HolidayCalendar {
+    /// The general statutory holidays of a Canadian province for the given years.
+    ///
+    /// Holidays falling on a weekend are observed on the next weekday that isn't already a
+    /// holiday, so Christmas on a Saturday is observed on Monday and Boxing Day on Tuesday.
+    /// Holidays some employers observe but provincial employment standards don't require
+    /// (e.g. Easter Monday) are left out; add them with a holiday file.
+    pub fn for_province(province: &str, years: &[i32]) -> Result<Self, ExcelError> {
+        let province = province.to_uppercase();
+        if !PROVINCES.contains(&province.as_str()) {
+            return Err(ExcelError::Msg(format!(
+                "unknown province `{}`, expected one of {}",
+                province,
+                PROVINCES.join(", ")
+            )));
+        }
+        let observed_in = |provinces: &[&str]| provinces.contains(&province.as_str());
+
+        let mut calendar = Self::default();
+        for year in years.iter().copied() {
+            let mut add = |date: Date<Utc>, name: &str| {
+                let mut observed = date;
+                while matches!(observed.weekday(), Weekday::Sat | Weekday::Sun)
+                    || calendar.holiday_on(observed).is_some()
+                {
+                    observed = observed.succ();
+                }
+                let name = if observed == date {
+                    name.to_string()
+                } else {
+                    format!("{} (observed)", name)
+                };
+                calendar.holidays.push(Holiday {
+                    date: observed,
+                    name,
+                })
+            };
+
+            add(date(year, 1, 1), "New Year's Day");
+            if observed_in(&["AB", "BC", "MB", "NB", "NS", "ON", "PE", "SK"]) {
+                add(nth_weekday(year, 2, Weekday::Mon, 3), "Family Day");
+            }
+            add(easter(year) - Duration::days(2), "Good Friday");
+            if observed_in(&["AB", "BC", "MB", "ON", "QC", "SK"]) {
+                add(victoria_day(year), "Victoria Day");
+            }
+            if observed_in(&["QC"]) {
+                add(date(year, 6, 24), "Saint-Jean-Baptiste Day");
+            }
+            add(date(year, 7, 1), "Canada Day");
+            if observed_in(&["BC", "NB", "SK"]) {
+                add(nth_weekday(year, 8, Weekday::Mon, 1), "Civic Holiday");
+            }
+            add(nth_weekday(year, 9, Weekday::Mon, 1), "Labour Day");
+            // A statutory holiday in PE from 2022 and in BC from 2023.
+            if (observed_in(&["PE"]) && year >= 2022) || (observed_in(&["BC"]) && year >= 2023) {
+                add(
+                    date(year, 9, 30),
+                    "National Day for Truth and Reconciliation",
+                );
+            }
+            if observed_in(&["AB", "BC", "MB", "ON", "QC", "SK"]) {
+                add(nth_weekday(year, 10, Weekday::Mon, 2), "Thanksgiving");
+            }
+            if observed_in(&["AB", "BC", "NB", "NL", "PE", "SK"]) {
+                add(date(year, 11, 11), "Remembrance Day");
+            }
+            add(date(year, 12, 25), "Christmas Day");
+            if observed_in(&["ON"]) {
+                add(date(year, 12, 26), "Boxing Day");
+            }
+        }
+
+        Ok(calendar)
+    }
+
+    /// Reads holidays from a file with `DATE` and `NAME` columns.
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut calendar = Self::default();
+        for record in records.iter() {
+            if let Some(date) = record.get_date("DATE")? {
+                calendar.holidays.push(Holiday {
+                    date,
+                    name: record.get_string("NAME"),
+                });
+            }
+        }
+
+        Ok(calendar)
+    }
+
+    pub fn extend(&mut self, other: HolidayCalendar) {
+        for holiday in other.holidays {
+            if self.holiday_on(holiday.date).is_none() {
+                self.holidays.push(holiday);
+            }
+        }
+    }
+
+    pub fn holiday_on(&self, date: Date<Utc>) -> Option<&Holiday> {
+        self.holidays.iter().find(|holiday| holiday.date == date)
+    }
+
+    /// The holidays that fall on one of the timecard's dates, in date order.
+    pub fn holidays_in(&self, date_range: &DateColumnRange) -> Vec<&Holiday> {
+        let mut holidays: Vec<&Holiday> = date_range
+            .clone()
+            .filter_map(|date| self.holiday_on(date))
+            .collect();
+        holidays.sort_by_key(|holiday| holiday.date);
+        holidays
+    }
+}
+
+/// How holiday hours are paid: the earning codes to use and how long an employee must
+/// have been employed to qualify.
+#[derive(Debug, Clone)]
+pub struct HolidayPay {
+    /// Earning code for hours worked on a holiday, usually paid at a premium rate.
+    pub worked_code: String,
+    /// Earning code for the holiday pay line given to eligible employees.
+    pub pay_code: String,
+    pub eligibility_days: i64,
+}
+
+impl HolidayPay {
+    /// Marks regular hours worked on a holiday with the worked-holiday earning code, then
+    /// adds a holiday pay line for each holiday to every eligible employee. Hours already
+    /// under another earning (e.g. vacation) keep it.
+    ///
+    /// Employees qualify when they've been employed for `eligibility_days` (or have no
+    /// hire date on file) and worked some other day in the period. They're paid their
+    /// average hours per day worked in the period, to the GL segments of their first line.
+    pub fn apply(
+        &self,
+        calendar: &HolidayCalendar,
+        date_range: &DateColumnRange,
+        employees: &mut [Employee],
+    ) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let holidays = calendar.holidays_in(date_range);
+        if holidays.is_empty() {
+            return issues;
+        }
+        for holiday in holidays.iter() {
+            info!(
+                "{} falls on {}",
+                holiday.name,
+                holiday.date.format(DATE_FORMAT)
+            );
+        }
+
+        for employee in employees.iter_mut() {
+            let mut worked_days = Vec::new();
+            let mut worked_hours = Duration::zero();
+            for shift in employee.hours.iter_mut() {
+                if calendar.holiday_on(shift.date).is_some() {
+                    if shift.earning == REGULAR_EARNING {
+                        shift.earning = self.worked_code.clone();
+                    }
+                } else if shift.sum_of_shift() > 0.0 {
+                    worked_hours = worked_hours + shift.duration;
+                    if !worked_days.contains(&shift.date) {
+                        worked_days.push(shift.date);
+                    }
+                }
+            }
+            if worked_days.is_empty() {
+                continue;
+            }
+
+            let average = worked_hours / worked_days.len() as i32;
+            for holiday in holidays.iter() {
+                if let Some(hire_date) = employee.hire_date {
+                    if holiday.date - hire_date < Duration::days(self.eligibility_days) {
+                        issues.push(Issue::warning(
+                            Some(&employee.id),
+                            format!(
+                                "not eligible for {} holiday pay, hired {}",
+                                holiday.name,
+                                hire_date.format(DATE_FORMAT)
+                            ),
+                        ));
+                        continue;
+                    }
+                }
+
+                let col = date_range.head
+                    + (holiday.date - date_range.start.unwrap()).num_days() as usize;
+                let mut shift = Shift::new(col, average, holiday.date);
+                shift.earning = self.pay_code.clone();
+                if let Some(first) = employee.hours.first() {
+                    shift.segments = first.segments.clone();
+                }
+                employee.hours.push(shift);
+            }
+            employee.hours.sort_by_key(|shift| shift.date);
+        }
+
+        issues
+    }
+}
+
+#[test]
+fn it_builds_provincial_calendars() {
+    let bc = HolidayCalendar::for_province("bc", &[2021]).unwrap();
+    assert_eq!(bc.holiday_on(date(2021, 2, 15)).unwrap().name, "Family Day");
+    assert_eq!(bc.holiday_on(date(2021, 4, 2)).unwrap().name, "Good Friday");
+    assert_eq!(
+        bc.holiday_on(date(2021, 5, 24)).unwrap().name,
+        "Victoria Day"
+    );
+    assert_eq!(
+        bc.holiday_on(date(2021, 8, 2)).unwrap().name,
+        "Civic Holiday"
+    );
+    assert!(bc.holiday_on(date(2021, 12, 26)).is_none());
+
+    let on = HolidayCalendar::for_province("ON", &[2021]).unwrap();
+    assert_eq!(
+        on.holiday_on(date(2021, 10, 11)).unwrap().name,
+        "Thanksgiving"
+    );
+    assert!(on.holiday_on(date(2021, 11, 11)).is_none());
+
+    assert!(HolidayCalendar::for_province("XX", &[2021]).is_err());
+}
+
+#[test]
+fn it_observes_weekend_holidays_on_weekdays() {
+    // Christmas 2021 was a Saturday and Boxing Day a Sunday.
+    let on = HolidayCalendar::for_province("ON", &[2021]).unwrap();
+    assert!(on.holiday_on(date(2021, 12, 25)).is_none());
+    assert_eq!(
+        on.holiday_on(date(2021, 12, 27)).unwrap().name,
+        "Christmas Day (observed)"
+    );
+    assert_eq!(
+        on.holiday_on(date(2021, 12, 28)).unwrap().name,
+        "Boxing Day (observed)"
+    );
+
+    let bc = HolidayCalendar::for_province("BC", &[2020, 2023]).unwrap();
+    let truth_and_reconciliation = |holiday: &Holiday| holiday.name.starts_with("National Day");
+    assert!(!bc
+        .holidays
+        .iter()
+        .any(|holiday| truth_and_reconciliation(holiday) && holiday.date.year() == 2020));
+    assert!(truth_and_reconciliation(
+        bc.holiday_on(date(2023, 10, 2)).unwrap()
+    ));
+}
+
+#[test]
+fn it_keeps_other_earnings_on_holidays() {
+    use crate::accruals::{Accrual, Bank, PayOrAccrue};
+
+    let victoria_day = date(2021, 5, 24);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(victoria_day);
+    date_range.end = Some(victoria_day + Duration::days(6));
+
+    let mut vacation = Shift::new(4, Duration::hours(8), victoria_day);
+    vacation.earning = "VAC".to_string();
+    vacation.segments[0].value = "200".to_string();
+    vacation.accrual = Some(Accrual {
+        bank: Bank::Vacation,
+        action: PayOrAccrue::Pay,
+    });
+    let mut employee = Employee::new("1001".to_string());
+    employee.hours = vec![
+        vacation,
+        Shift::new(4, Duration::hours(2), victoria_day),
+        Shift::new(5, Duration::hours(8), victoria_day + Duration::days(1)),
+    ];
+    let mut employees = vec![employee];
+
+    let holiday_pay = HolidayPay {
+        worked_code: "STATWK".to_string(),
+        pay_code: "STAT".to_string(),
+        eligibility_days: 30,
+    };
+    let calendar = HolidayCalendar::for_province("BC", &[2021]).unwrap();
+    assert!(holiday_pay
+        .apply(&calendar, &date_range, &mut employees)
+        .is_empty());
+
+    let earnings: Vec<&str> = employees[0]
+        .hours
+        .iter()
+        .map(|shift| shift.earning.as_str())
+        .collect();
+    assert_eq!(earnings, vec!["VAC", "STATWK", "STAT", "HRLY"]);
+    assert!(employees[0].hours[0].accrual.is_some());
+    assert_eq!(employees[0].hours[2].segments[0].value, "200");
+}
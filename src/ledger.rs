@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::accruals::{bank_hours, Bank, PayOrAccrue};
+use crate::employees::Employee;
+use crate::validation::Issue;
+
+/// Hours taken over a balance by less than this are ignored, to allow for float rounding.
+const HOURS_TOLERANCE: f32 = 0.005;
+
+#[derive(Error, Debug)]
+pub enum LedgerError {
+    #[error("io error")]
+    Io(std::io::Error),
+    #[error("json error")]
+    Json(serde_json::Error),
+}
+
+/// Vacation, sick, comp time and disability balances in hours, kept from one pay period to
+/// the next in a JSON file.
+///
+/// Opening balances can be added by hand under `employees`, e.g.
+/// `{"employees": {"1001": {"vacation": 40.0, "sick": 16.0}}}`. Each exported period then
+/// takes the hours paid from a bank off its balance and adds the hours accrued to it.
+///
+/// What each employee's balances were changed by is kept under `posted` by period end, so
+/// an employee's period is only posted once, however many timecards the period is split
+/// across, and a correction posts only the difference.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    #[serde(default)]
+    pub employees: BTreeMap<String, BTreeMap<Bank, f32>>,
+    #[serde(default)]
+    pub posted: BTreeMap<String, BTreeMap<String, BTreeMap<Bank, f32>>>,
+}
+
+/// How much an employee's hours change each bank's balance by.
+fn balance_changes(employee: &Employee) -> BTreeMap<Bank, f32> {
+    let mut changes = BTreeMap::new();
+    for ((bank, action), hours) in bank_hours(&employee.hours) {
+        let change = changes.entry(bank).or_insert(0.0);
+        match action {
+            PayOrAccrue::Pay => *change -= hours,
+            PayOrAccrue::Accrue => *change += hours,
+        }
+    }
+    changes
+}
+
+impl Ledger {
+    /// Reads the ledger, starting an empty one when the file doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self, LedgerError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let file = File::open(path).map_err(LedgerError::Io)?;
+        serde_json::from_reader(file).map_err(LedgerError::Json)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), LedgerError> {
+        let file = File::create(path).map_err(LedgerError::Io)?;
+        serde_json::to_writer_pretty(file, self).map_err(LedgerError::Json)
+    }
+
+    pub fn balance(&self, id: &str, bank: Bank) -> f32 {
+        self.employees
+            .get(id)
+            .and_then(|balances| balances.get(&bank))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// What the employee's balances were changed by when the period was posted.
+    fn posted(&self, id: &str, period_end: &str) -> Option<&BTreeMap<Bank, f32>> {
+        self.posted
+            .get(id)
+            .and_then(|periods| periods.get(period_end))
+    }
+
+    /// Flags employees taking more hours from a bank than its balance, counting hours
+    /// accrued in the same period. When correcting a period, what was posted for it is
+    /// taken back out of the balances first so its hours aren't counted twice.
+    pub fn check(&self, employees: &[Employee], correcting: Option<&str>) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for employee in employees.iter() {
+            let posted = correcting.and_then(|period_end| self.posted(&employee.id, period_end));
+            let hours = bank_hours(&employee.hours);
+            for ((bank, action), taken) in hours.iter() {
+                if *action != PayOrAccrue::Pay {
+                    continue;
+                }
+
+                let accrued = hours
+                    .get(&(*bank, PayOrAccrue::Accrue))
+                    .copied()
+                    .unwrap_or_default();
+                let reversed = posted
+                    .and_then(|posted| posted.get(bank))
+                    .copied()
+                    .unwrap_or_default();
+                let available = self.balance(&employee.id, *bank) - reversed + accrued;
+                if taken - available > HOURS_TOLERANCE {
+                    issues.push(Issue::warning(
+                        Some(&employee.id),
+                        format!(
+                            "takes {:.2} {} hours but has {:.2} available",
+                            taken,
+                            bank.name(),
+                            available
+                        ),
+                    ));
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Applies a period's paid and accrued hours to the balances. Employees whose period
+    /// was already posted are left alone and returned, unless this is a correction of the
+    /// timecard with the `corrected` employees, which posts the difference from what was
+    /// posted before and reverses the postings of employees no longer on it.
+    pub fn post(
+        &mut self,
+        period_end: &str,
+        employees: &[Employee],
+        corrected: Option<&[String]>,
+    ) -> Vec<String> {
+        let mut skipped = Vec::new();
+
+        let mut postings: Vec<(String, BTreeMap<Bank, f32>)> = employees
+            .iter()
+            .map(|employee| (employee.id.clone(), balance_changes(employee)))
+            .collect();
+        for id in corrected.unwrap_or_default() {
+            if !postings.iter().any(|(posting, _)| posting == id) {
+                postings.push((id.clone(), BTreeMap::new()));
+            }
+        }
+
+        for (id, changes) in postings {
+            let previous = match self.posted(&id, period_end) {
+                Some(posted) if corrected.is_some() => posted.clone(),
+                Some(_) => {
+                    if !changes.is_empty() {
+                        skipped.push(id);
+                    }
+                    continue;
+                }
+                None if changes.is_empty() => continue,
+                None => BTreeMap::new(),
+            };
+
+            let balances = self.employees.entry(id.clone()).or_default();
+            let banks: BTreeSet<Bank> = changes.keys().chain(previous.keys()).copied().collect();
+            for bank in banks {
+                *balances.entry(bank).or_default() +=
+                    changes.get(&bank).copied().unwrap_or_default()
+                        - previous.get(&bank).copied().unwrap_or_default();
+            }
+            self.posted
+                .entry(id)
+                .or_default()
+                .insert(period_end.to_string(), changes);
+        }
+
+        skipped
+    }
+
+    /// Logs every employee's closing balances.
+    pub fn report(&self) {
+        for (id, balances) in self.employees.iter() {
+            let balances: Vec<String> = balances
+                .iter()
+                .map(|(bank, hours)| format!("{} {:.2}", bank.name(), hours))
+                .collect();
+            info!("Balances for `{}`: {}", id, balances.join(", "));
+        }
+    }
+}
+
+#[test]
+fn it_posts_banked_hours_once() {
+    use crate::accruals::Accrual;
+    use crate::employees::Shift;
+    use chrono::{Duration, TimeZone, Utc};
+
+    let mut ledger: Ledger =
+        serde_json::from_str(r#"{"employees": {"1001": {"vacation": 4.0}}}"#).unwrap();
+
+    let banked = |hours: i64, bank: Bank, action: PayOrAccrue| {
+        let mut shift = Shift::new(4, Duration::hours(hours), Utc.ymd(2021, 5, 3));
+        shift.accrual = Some(Accrual { bank, action });
+        shift
+    };
+    let mut employee = Employee::new("1001".to_string());
+    employee.hours = vec![
+        banked(8, Bank::Vacation, PayOrAccrue::Pay),
+        banked(3, Bank::Comp, PayOrAccrue::Accrue),
+    ];
+    let employees = vec![employee];
+
+    let issues = ledger.check(&employees, None);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(
+        issues[0].message,
+        "takes 8.00 vacation hours but has 4.00 available"
+    );
+
+    assert!(ledger.post("2021-05-08", &employees, None).is_empty());
+    assert_eq!(ledger.post("2021-05-08", &employees, None), vec!["1001"]);
+    assert_eq!(ledger.balance("1001", Bank::Vacation), -4.0);
+    assert_eq!(ledger.balance("1001", Bank::Comp), 3.0);
+
+    // Another department's timecard for the same period still posts.
+    let mut other = Employee::new("1002".to_string());
+    other.hours = vec![banked(4, Bank::Sick, PayOrAccrue::Pay)];
+    assert!(ledger.post("2021-05-08", &[other], None).is_empty());
+    assert_eq!(ledger.balance("1002", Bank::Sick), -4.0);
+
+    // A correction is checked against the balances from before the period.
+    let mut corrected = employees[0].clone();
+    corrected.hours = vec![banked(6, Bank::Vacation, PayOrAccrue::Pay)];
+    let issues = ledger.check(&[corrected.clone()], Some("2021-05-08"));
+    assert_eq!(
+        issues[0].message,
+        "takes 6.00 vacation hours but has 4.00 available"
+    );
+
+    // It posts only the difference from what was posted, and reverses the postings of
+    // employees taken off the timecard.
+    let ids = ["1001".to_string(), "1002".to_string()];
+    assert!(ledger
+        .post("2021-05-08", &[corrected], Some(&ids))
+        .is_empty());
+    assert_eq!(ledger.balance("1002", Bank::Sick), 0.0);
+    assert_eq!(ledger.balance("1001", Bank::Vacation), -2.0);
+    assert_eq!(ledger.balance("1001", Bank::Comp), 0.0);
+    assert_eq!(
+        serde_json::to_string(&ledger.posted["1001"]).unwrap(),
+        r#"{"2021-05-08":{"vacation":-6.0}}"#
+    );
+}
@@ -0,0 +1,101 @@
+use crate::employees::Shift;
+
+/// Sage timecard lines are numbered in steps, leaving room to insert lines between them.
+pub const LINE_NUMBER_STEP: usize = 1000;
+
+/// A timecard detail line: an employee's hours for one day under one earning code, job,
+/// shift, rate and set of GL segments, or a single stretch of clock time.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub number: usize,
+    pub shift: Shift,
+}
+
+/// Hours given as clock times stay on their own lines so their start and stop times are
+/// kept; other hours for the same day and codes are added together.
+fn same_line(a: &Shift, b: &Shift) -> bool {
+    a.start.is_none()
+        && b.start.is_none()
+        && a.date == b.date
+        && a.earning == b.earning
+        && a.job == b.job
+        && a.shift_number == b.shift_number
+        && a.segments == b.segments
+        && a.rate == b.rate
+        && a.accrual == b.accrual
+}
+
+/// Builds an employee's detail lines ordered by date, earning code and job, numbering only
+/// the lines that are written.
+pub fn build_lines(shifts: &[Shift]) -> Vec<Line> {
+    let mut shifts: Vec<&Shift> = shifts
+        .iter()
+        .filter(|shift| shift.sum_of_shift() > 0.0)
+        .collect();
+    // A stable sort, so pieces of a shift that runs past midnight stay in order.
+    shifts.sort_by(|a, b| {
+        a.date
+            .cmp(&b.date)
+            .then_with(|| a.earning.cmp(&b.earning))
+            .then_with(|| a.job.cmp(&b.job))
+    });
+
+    let mut lines: Vec<Line> = Vec::new();
+    for shift in shifts {
+        if let Some(line) = lines.iter_mut().find(|line| same_line(&line.shift, shift)) {
+            line.shift.duration = line.shift.duration + shift.duration;
+            continue;
+        }
+
+        lines.push(Line {
+            number: (lines.len() + 1) * LINE_NUMBER_STEP,
+            shift: shift.clone(),
+        });
+    }
+
+    lines
+}
+
+#[test]
+fn it_numbers_emitted_lines() {
+    use chrono::{Duration, NaiveTime, TimeZone, Utc};
+
+    let today = Utc.ymd(2021, 5, 3);
+    let shift = |hours: i64, days: i64, earning: &str| {
+        let mut shift = Shift::new(4, Duration::hours(hours), today + Duration::days(days));
+        shift.earning = earning.to_string();
+        shift
+    };
+    let mut clocked = shift(3, 1, "HRLY");
+    clocked.start = NaiveTime::from_hms_opt(18, 0, 0);
+
+    let lines = build_lines(&[
+        shift(4, 1, "SICK"),
+        shift(0, 0, "HRLY"),
+        shift(4, 1, "HRLY"),
+        shift(8, 0, "HRLY"),
+        clocked,
+        shift(2, 1, "HRLY"),
+    ]);
+
+    let summary: Vec<(usize, i64, &str, f32)> = lines
+        .iter()
+        .map(|line| {
+            (
+                line.number,
+                (line.shift.date - today).num_days(),
+                line.shift.earning.as_str(),
+                line.shift.sum_of_shift(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (1000, 0, "HRLY", 8.0),
+            (2000, 1, "HRLY", 6.0),
+            (3000, 1, "HRLY", 3.0),
+            (4000, 1, "SICK", 4.0),
+        ]
+    );
+}
@@ -2,64 +2,336 @@
 extern crate log;
 extern crate chrono;
 
+use std::io;
+use std::path::{Path, PathBuf};
+
 use anyhow::Context;
+use calamine::Reader;
+use chrono::Datelike;
+use crossterm::tty::IsTty;
 use env_logger::Env;
-use excel::timecards::DateColumnRange;
+use excel::timecards::{DateColumnRange, Layout};
+use structopt::clap::{AppSettings, Error, ErrorKind};
 use structopt::StructOpt;
 
 use crate::{
-    employees::sum_of_hours,
-    excel::{from_column_letter, to_column_letter},
+    accounts::{ChartOfAccounts, DistributionCodes, SegmentList},
+    accruals::AccrualRules,
+    cost::{CostRates, LabourCost},
+    employees::{sum_of_hours, Employee},
+    excel::{
+        from_column_letter,
+        sage_import::{is_sage_import, read_sage_import, SageTimecard},
+    },
+    export::PayPeriod,
+    history::{hash_file, History, Run},
+    holidays::{HolidayCalendar, HolidayPay},
+    ledger::Ledger,
+    master::EmployeeMaster,
+    preview::Format,
+    rates::JobRates,
+    review::{Edit, Review},
+    server::{Api, Outcome, Upload},
+    shifts::ShiftRules,
+    summary::Summary,
+    timecard::TimecardTemplate,
+    validation::{Issue, Severity, ValidationFailed},
+    watch::Inbox,
 };
 
+mod accounts;
+mod accruals;
+mod correction;
+mod cost;
+mod diff;
 mod employees;
 mod excel;
+mod export;
+mod history;
+mod holidays;
+mod ledger;
+mod lines;
+mod master;
+mod preview;
+mod rates;
+mod report;
+mod review;
+mod server;
+mod shifts;
+mod summary;
+mod timecard;
+mod validation;
+mod watch;
 
 const DATE_FORMAT: &str = "%B %d, %Y";
 
-#[derive(Debug, StructOpt)]
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(setting = AppSettings::SubcommandsNegateReqs)]
 struct Cli {
-    sheet: String,
+    sheet: Option<String>,
     #[structopt(parse(from_os_str))]
-    path: std::path::PathBuf,
+    path: Option<PathBuf>,
     output: Option<String>,
     #[structopt(short = "v")]
     verbose: bool,
+    /// Employee master file (csv or xlsx) keyed by the `EMPLOYEE` column
+    #[structopt(long = "employees", parse(from_os_str))]
+    employees: Option<std::path::PathBuf>,
+    /// Column letter holding a job or project code, with one row per employee per job. The
+    /// code is written to the line's description only, not as a Sage job allocation
+    #[structopt(long = "job-column")]
+    job_column: Option<String>,
+    /// Column letter holding the earning code for a row's hours, with one row per employee
+    /// per earning
+    #[structopt(long = "earning-column")]
+    earning_column: Option<String>,
+    /// Column letter holding an hourly rate for the row's hours
+    #[structopt(long = "rate-column")]
+    rate_column: Option<String>,
+    /// Job rate table (csv or xlsx) with `JOB`, `RATE` and optional `EMPLOYEE` columns
+    #[structopt(long = "job-rates", parse(from_os_str))]
+    job_rates: Option<std::path::PathBuf>,
+    /// Write every employee's rates to the detail lines, overriding the rates set up in
+    /// Sage (the master file's `RRATEOVER` column does this per employee)
+    #[structopt(long = "override-rates")]
+    override_rates: bool,
+    /// Column letters holding GL segment overrides for `GLSEG1` to `GLSEG3`, comma separated
+    #[structopt(long = "segment-columns", use_delimiter = true)]
+    segment_columns: Vec<String>,
+    /// GL segment list (csv or xlsx) with `GLSEG`, `SEGMENTID`, `VALUE` and `DESC` columns
+    #[structopt(long = "segments", parse(from_os_str))]
+    segments: Option<std::path::PathBuf>,
+    /// Distribution code table (csv or xlsx) mapping `DISTCODE` to `EXPACCT`, `LIABACCT`,
+    /// `OTACCT`, `SHIFTACCT` and `OTSCHED`
+    #[structopt(long = "dist-codes", parse(from_os_str))]
+    dist_codes: Option<std::path::PathBuf>,
+    /// Chart of accounts exported from Sage GL (csv or xlsx), used to check every account
+    #[structopt(long = "accounts", parse(from_os_str))]
+    accounts: Option<std::path::PathBuf>,
+    /// Accrual rules (csv or xlsx) with `EARNDED`, `BANK`, `ACTION` and optional `CODE` and
+    /// `OTSCHED` columns, marking hours paid from or accrued to a vacation, sick or comp bank
+    #[structopt(long = "accrual-rules", parse(from_os_str))]
+    accrual_rules: Option<std::path::PathBuf>,
+    /// Shift differential rules (csv or xlsx) with `SHIFTNUM`, `CODE`, `DAYS`, `START` and
+    /// `STOP` columns
+    #[structopt(long = "shift-rules", parse(from_os_str))]
+    shift_rules: Option<std::path::PathBuf>,
+    /// Province whose statutory holidays apply (AB, BC, MB, NB, NL, NS, ON, PE, QC or SK)
+    #[structopt(long = "province")]
+    province: Option<String>,
+    /// Extra holidays (csv or xlsx) with `DATE` and `NAME` columns
+    #[structopt(long = "holidays", parse(from_os_str))]
+    holidays: Option<std::path::PathBuf>,
+    /// Earning code for hours worked on a statutory holiday
+    #[structopt(long = "holiday-worked-code", default_value = "STATWK")]
+    holiday_worked_code: String,
+    /// Earning code for holiday pay lines
+    #[structopt(long = "holiday-pay-code", default_value = "STAT")]
+    holiday_pay_code: String,
+    /// Days an employee must have been employed before a holiday to receive holiday pay
+    #[structopt(long = "holiday-eligibility-days", default_value = "30")]
+    holiday_eligibility_days: i64,
+    /// Only warn, rather than fail, when terminated or inactive employees have hours
+    #[structopt(long = "allow-inactive")]
+    allow_inactive: bool,
+    /// Print the rows that would be imported into Sage instead of writing a workbook
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+    /// Export without opening the review screen. It is also skipped for `--dry-run` and when
+    /// not run from a terminal
+    #[structopt(long = "no-review", alias = "yes")]
+    no_review: bool,
+    /// Output format for `--dry-run`: table or json
+    #[structopt(long = "format", default_value = "table")]
+    format: Format,
+    /// Write the parsed pay period, with its validation issues, to this file as JSON (or
+    /// YAML when it ends in `.yaml` or `.yml`), unless it's a dry run
+    #[structopt(long = "emit-json", parse(from_os_str))]
+    emit_json: Option<std::path::PathBuf>,
+    /// Add a Summary sheet with each employee's hours by day for managers to sign off on
+    #[structopt(long = "summary")]
+    summary: bool,
+    /// Weekly hours after which the summary counts hours as overtime
+    #[structopt(long = "overtime-hours", default_value = "40")]
+    overtime_hours: f32,
+    /// Vacation, sick and comp time balance ledger (JSON), checked against the hours taken
+    /// and updated with the period once the Sage import is written
+    #[structopt(long = "ledger", parse(from_os_str))]
+    ledger: Option<PathBuf>,
+    /// Run history (JSON) recording each export, used to stop a period from being exported
+    /// twice for the same employees
+    #[structopt(long = "history", parse(from_os_str))]
+    history: Option<PathBuf>,
+    /// Only warn when the run history shows the period was already exported
+    #[structopt(long = "allow-duplicate")]
+    allow_duplicate: bool,
+    /// Write an estimate of gross pay and employer labour cost per employee, dist code and
+    /// GL account to this CSV file
+    #[structopt(long = "cost-report", parse(from_os_str))]
+    cost_report: Option<PathBuf>,
+    /// What overtime hours are paid at in the cost report, as a multiple of the rate
+    #[structopt(long = "overtime-multiplier", default_value = "1.5")]
+    overtime_multiplier: f64,
+    /// Employer costs on top of gross pay in the cost report, as a percentage
+    #[structopt(long = "burden-percent", default_value = "0")]
+    burden_percent: f64,
+    /// Write an HTML approval report, grouped by dist code, alongside the Sage import
+    #[structopt(long = "approval-report", parse(from_os_str))]
+    approval_report: Option<std::path::PathBuf>,
+    /// Sage import workbook generated for this timecard before it was corrected; only the
+    /// lines needed to update, add or delete what was imported from it are written
+    #[structopt(long = "correct", parse(from_os_str))]
+    correct: Option<PathBuf>,
+    /// Template for the Sage timecard id, using `{sheet}`, `{start}`, `{end}` (e.g.
+    /// `{end:%m%d}`) and `{dist}`, which like `{sheet}` can be cut short (e.g. `{dist:.2}`)
+    #[structopt(long = "timecard-id", default_value = "{end:%y%m%d}")]
+    timecard_id: String,
+    /// Template for the Sage timecard description, with the same placeholders as the id
+    #[structopt(long = "timecard-desc", default_value = "")]
+    timecard_desc: String,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+enum Command {
+    /// Compare two versions of a timecard workbook and list what changed, applying the same
+    /// lookup files as an export. Either version can be a generated Sage import workbook,
+    /// which is read as is.
+    Diff {
+        sheet: String,
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+    },
+    /// List the exports recorded in a run history file, or show one of them in detail
+    History {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// Number of the run to show, as listed
+        run: Option<usize>,
+    },
+    /// Watch a folder for timecard workbooks and convert each one as it arrives, using the
+    /// same options as an export. Each Sage import is written to the outbox with a log and
+    /// an approval report; workbooks that fail are moved to the error folder with the reason.
+    Watch {
+        #[structopt(parse(from_os_str))]
+        inbox: PathBuf,
+        #[structopt(parse(from_os_str))]
+        outbox: PathBuf,
+        #[structopt(parse(from_os_str))]
+        errors: PathBuf,
+        /// Worksheet to convert, the first worksheet of each workbook when not given
+        #[structopt(long = "sheet")]
+        sheet: Option<String>,
+        /// Seconds between checks of the inbox
+        #[structopt(long = "interval", default_value = "10")]
+        interval: u64,
+        /// Convert the workbooks already in the inbox and exit
+        #[structopt(long = "once")]
+        once: bool,
+    },
+    /// Serve a local HTTP API: POST a workbook to `/timecards?sheet=...` to convert it with
+    /// the same options as an export, then download the Sage import from `/imports/<id>.xlsx`,
+    /// or its sheets from `/imports/<id>-header.csv` and `/imports/<id>-detail.csv`
+    Serve {
+        #[structopt(long = "address", default_value = "127.0.0.1:8080")]
+        address: String,
+        /// Hours to keep uploads and Sage imports for before they are deleted
+        #[structopt(long = "keep-hours", default_value = "24")]
+        keep_hours: u64,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
-    let mut args = Cli::from_args();
+    let args = Cli::from_args();
 
     let env = Env::default().filter_or("RUST_LOG", if args.verbose { "TRACE" } else { "INFO" });
     env_logger::init_from_env(env);
 
-    let workbook = &mut calamine::open_workbook_auto(args.path.clone())
-        .with_context(|| format!("could not open excel workbook at `{:?}`", args.path.clone()))?;
-    trace!("opened excel workbook {:?}", args.path);
+    match &args.command {
+        Some(Command::Diff { sheet, old, new }) => return diff_timecards(&args, sheet, old, new),
+        Some(Command::History { file, run }) => return show_history(file, *run),
+        Some(Command::Watch { .. }) => return watch_inbox(&args),
+        Some(Command::Serve {
+            address,
+            keep_hours,
+        }) => return serve_api(&args, address, *keep_hours),
+        None => {}
+    }
+    let (sheet, path) = match (args.sheet.clone(), args.path.clone()) {
+        (Some(sheet), Some(path)) => (sheet, path),
+        _ => Error::with_description(
+            "the worksheet name and the workbook path are required",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit(),
+    };
 
-    let mut date_range =
-        excel::timecards::parse_date_range(workbook, &args.sheet).with_context(|| {
-            format!(
-                "failed to parse dates from workbook sheet `{}`",
-                &args.sheet
-            )
-        })?;
-    date_range = date_range_correct_confirmation(&args, &mut date_range)?.clone();
+    let review = !args.no_review && !args.dry_run && io::stdin().is_tty() && io::stdout().is_tty();
+    let (date_range, employees_vec, edits) = if review {
+        review_timecard(&args, &sheet, &path)?
+    } else {
+        let (date_range, employees_vec) = read_timecard(&args, &sheet, &path, None)?;
+        (date_range, employees_vec, Vec::new())
+    };
+    export_timecard(&args, &sheet, &path, date_range, employees_vec, &edits)?;
 
-    let employees_vec =
-        excel::timecards::parse_worksheet(workbook, &args.sheet, date_range.clone()).with_context(
-            || {
-                format!(
-                    "failed to parse employee data from workbook sheet `{}`",
-                    &args.sheet
-                )
-            },
-        )?;
+    Ok(())
+}
 
-    if args.output.is_none() {
-        args.output = Some("output.xlsx".to_string());
+/// Runs the rest of the pipeline for a timecard worksheet that was read, with any edits
+/// made to it on the review screen: checks it, then writes the Sage import and every other
+/// output asked for. Returns the issues found, or a [`ValidationFailed`] error when any of
+/// them are errors.
+fn export_timecard(
+    args: &Cli,
+    sheet: &str,
+    path: &Path,
+    date_range: DateColumnRange,
+    mut employees_vec: Vec<Employee>,
+    edits: &[Edit],
+) -> anyhow::Result<Vec<Issue>> {
+    let mut issues = apply_lookups(args, sheet, &date_range, &mut employees_vec)?;
+    let (_, end) = date_range
+        .range()
+        .with_context(|| "timecard has no date range")?;
+    let period_end = end.format("%Y-%m-%d").to_string();
+    let previous = match &args.correct {
+        Some(path) => Some(
+            read_sage_import(path)
+                .with_context(|| format!("failed to read Sage import workbook `{:?}`", path))?,
+        ),
+        None => None,
+    };
+    let mut ledger = match &args.ledger {
+        Some(path) => {
+            let ledger = Ledger::load(path)
+                .with_context(|| format!("failed to load balance ledger `{:?}`", path))?;
+            let correcting = previous.as_ref().map(|_| period_end.as_str());
+            issues.extend(ledger.check(&employees_vec, correcting));
+            Some(ledger)
+        }
+        None => None,
+    };
+    let error_count = validation::report(&issues);
+    // A dry run writes nothing, only printing the rows that would be imported.
+    if let (Some(path), false) = (&args.emit_json, args.dry_run) {
+        PayPeriod::new(sheet, &date_range, &employees_vec, &issues)
+            .and_then(|period| period.write(path))
+            .with_context(|| format!("failed to write pay period to `{:?}`", path))?;
+    }
+    if error_count > 0 {
+        return Err(ValidationFailed(issues).into());
     }
 
+    timecard::report_ids(&employees_vec);
+    let filename = args
+        .output
+        .clone()
+        .unwrap_or_else(|| "output.xlsx".to_string());
+
     info!(
         "Total employees: {}",
         employees_vec
@@ -74,77 +346,659 @@ fn main() -> anyhow::Result<()> {
         }
     }
 
-    let filename = args
-        .output
-        .with_context(|| "output filename was specified but is blank!")?;
+    let summary = Summary::new(&employees_vec, &date_range, args.overtime_hours);
+    let cost = LabourCost::new(
+        &employees_vec,
+        &date_range,
+        CostRates {
+            weekly_overtime: args.overtime_hours,
+            overtime_multiplier: args.overtime_multiplier,
+            burden_percent: args.burden_percent,
+        },
+    );
+    let history = match &args.history {
+        Some(history_path) => {
+            let history = History::load(history_path)
+                .with_context(|| format!("failed to load run history `{:?}`", history_path))?;
+            let mut run = Run::new(
+                path,
+                hash_file(path).with_context(|| format!("failed to read `{:?}`", path))?,
+                sheet,
+                &period_end,
+                &filename,
+                args.correct.is_some(),
+                &employees_vec,
+            );
+            run.edits = edits.iter().map(Edit::to_string).collect();
+            if !run.correction {
+                check_duplicates(&history, &run, args.allow_duplicate || args.dry_run)?;
+            }
+            Some((history, run))
+        }
+        None => None,
+    };
+    if let Some(ledger) = &mut ledger {
+        let corrected: Option<Vec<String>> = previous.as_ref().map(|previous| {
+            previous
+                .header
+                .iter()
+                .filter_map(|row| row.get("EMPLOYEE"))
+                .map(String::from)
+                .collect()
+        });
+        let skipped = ledger.post(&period_end, &employees_vec, corrected.as_deref());
+        if !skipped.is_empty() {
+            warn!(
+                "The period ending {} is already in the balance ledger for employees {}, their balances are left as they are",
+                period_end,
+                skipped.join(", ")
+            );
+        }
+    }
+    let mut import = excel::sage::build(sheet, employees_vec, &date_range)
+        .with_context(|| "an error occurred while trying try generate spreadsheet")?;
+    if let Some(previous) = &previous {
+        import = correction::corrections(previous, &import);
+        info!("Correction lines: {}", import.detail.len());
+    }
+    if args.dry_run {
+        preview::print(&import, args.format)
+            .with_context(|| "an error occurred while printing the dry run")?;
+        return Ok(issues);
+    }
+
     let workbook = xlsxwriter::Workbook::new(&filename);
-    excel::sage::generate(workbook, &args.sheet, employees_vec, date_range)
+    excel::sage::write(workbook, &import, args.summary.then_some(&summary))
         .with_context(|| "an error occurred while trying try generate spreadsheet")?;
+    if !edits.is_empty() {
+        // Kept next to the import whether or not there is a run history to record them in.
+        let path = Path::new(&filename).with_extension("edits.txt");
+        let lines: Vec<String> = edits.iter().map(|edit| format!("{}\n", edit)).collect();
+        std::fs::write(&path, lines.concat())
+            .with_context(|| format!("failed to write review edits to `{:?}`", path))?;
+        info!("Review edits written to {:?}", path);
+    }
+
+    if let (Some((mut history, run)), Some(path)) = (history, &args.history) {
+        history.record(run);
+        history
+            .save(path)
+            .with_context(|| format!("failed to save run history `{:?}`", path))?;
+    }
+    if let (Some(ledger), Some(path)) = (&ledger, &args.ledger) {
+        ledger
+            .save(path)
+            .with_context(|| format!("failed to save balance ledger `{:?}`", path))?;
+        ledger.report();
+    }
+    if let Some(path) = &args.cost_report {
+        let unpriced = cost.unpriced();
+        if !unpriced.is_empty() {
+            warn!(
+                "Employees with hours but no rate are left out of the cost report: {}",
+                unpriced.join(", ")
+            );
+        }
+        cost.write_csv(path)
+            .with_context(|| format!("failed to write cost report to `{:?}`", path))?;
+    }
+    if let Some(path) = &args.approval_report {
+        let html = report::approval_report(sheet, &summary)
+            .with_context(|| "an error occurred while rendering the approval report")?;
+        std::fs::write(path, html)
+            .with_context(|| format!("failed to write approval report to `{:?}`", path))?;
+    }
+
+    Ok(issues)
+}
+
+fn watch_inbox(args: &Cli) -> anyhow::Result<()> {
+    let (inbox_dir, outbox, errors, sheet, interval, once) = match &args.command {
+        Some(Command::Watch {
+            inbox,
+            outbox,
+            errors,
+            sheet,
+            interval,
+            once,
+        }) => (inbox, outbox, errors, sheet, *interval, *once),
+        _ => unreachable!(),
+    };
+    for dir in [outbox, errors] {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create folder `{:?}`", dir))?;
+    }
+
+    info!("Watching {:?} for timecard workbooks", inbox_dir);
+    let mut inbox = Inbox::new(inbox_dir.clone());
+    // The first check only notes what is there, so give it a moment to settle.
+    inbox
+        .ready()
+        .with_context(|| format!("failed to read inbox `{:?}`", inbox_dir))?;
+    loop {
+        let wait = if once { 1 } else { interval };
+        std::thread::sleep(std::time::Duration::from_secs(wait));
+        // The inbox may be on a share that drops out for a while, which is no reason to stop.
+        let ready = match inbox.ready() {
+            Ok(ready) => ready,
+            Err(err) => {
+                error!("Failed to read inbox {:?}: {}", inbox_dir, err);
+                continue;
+            }
+        };
+
+        for path in ready {
+            if let Err(err) =
+                handle_dropped(args, sheet.as_deref(), &path, inbox_dir, outbox, errors)
+            {
+                error!(
+                    "Failed to handle {:?}, leaving it in the inbox: {:#}",
+                    path, err
+                );
+            }
+            inbox.forget(&path);
+        }
+
+        if once {
+            return Ok(());
+        }
+    }
+}
+
+/// Converts a workbook from the inbox and moves it to the processed or error folder. An
+/// error means the workbook couldn't be moved or its log written, e.g. because it is
+/// still open on a share, so it is tried again once it settles.
+fn handle_dropped(
+    args: &Cli,
+    sheet: Option<&str>,
+    path: &Path,
+    inbox_dir: &Path,
+    outbox: &Path,
+    errors: &Path,
+) -> anyhow::Result<()> {
+    info!("Converting {:?}", path);
+    let log_path = watch::output_path(outbox, path, ".log");
+    match convert_dropped(args, sheet, path, outbox) {
+        Ok((sheet, issues)) => {
+            let mut log = format!("Converted `{}` of {:?}\n", sheet, path);
+            for issue in issues.iter() {
+                log.push_str(&format!("{:?}: {}\n", issue.severity, issue));
+            }
+            std::fs::write(&log_path, log)
+                .with_context(|| format!("failed to write log `{:?}`", log_path))?;
+            watch::move_into(path, &inbox_dir.join("processed"))
+                .with_context(|| format!("failed to move {:?} out of the inbox", path))?;
+        }
+        Err(err) => {
+            error!("Failed to convert {:?}: {:#}", path, err);
+            let mut reason = format!("{:#}\n", err);
+            if let Some(ValidationFailed(issues)) = err.downcast_ref::<ValidationFailed>() {
+                for issue in issues.iter() {
+                    reason.push_str(&format!("{:?}: {}\n", issue.severity, issue));
+                }
+            }
+            let moved = watch::move_into(path, errors)
+                .with_context(|| format!("failed to move {:?} out of the inbox", path))?;
+            let reason_path = watch::output_path(errors, &moved, ".reason.txt");
+            std::fs::write(&reason_path, reason)
+                .with_context(|| format!("failed to write failure reason `{:?}`", reason_path))?;
+        }
+    }
 
     Ok(())
 }
 
-fn date_range_correct_confirmation<'a>(
-    args: &'a Cli,
-    date_range: &'a mut DateColumnRange,
-) -> anyhow::Result<&'a DateColumnRange> {
-    let (ref mut start, ref mut end) = date_range.range().with_context(|| {
-        format!(
-            "failed to parse dates from workbook sheet `{}`",
-            &args.sheet
-        )
-    })?;
+/// Converts a workbook dropped in the watched inbox, writing its outputs to the outbox
+/// under the workbook's name.
+fn convert_dropped(
+    args: &Cli,
+    sheet: Option<&str>,
+    path: &Path,
+    outbox: &Path,
+) -> anyhow::Result<(String, Vec<Issue>)> {
+    let sheet = match sheet {
+        Some(sheet) => sheet.to_string(),
+        None => first_sheet(path)?,
+    };
 
-    for date in date_range.clone() {
-        trace!("{}", date.format(DATE_FORMAT));
+    let mut args = args.clone();
+    let output = watch::output_path(outbox, path, "-sage.xlsx");
+    args.output = Some(output.display().to_string());
+    args.approval_report = Some(watch::output_path(outbox, path, ".html"));
+    if args.cost_report.is_some() {
+        args.cost_report = Some(watch::output_path(outbox, path, "-cost.csv"));
     }
-    println!(
-        "From {} to {}",
-        start.format(DATE_FORMAT),
-        end.format(DATE_FORMAT)
+    if args.emit_json.is_some() {
+        args.emit_json = Some(watch::output_path(outbox, path, ".json"));
+    }
+    args.dry_run = false;
+
+    let (date_range, employees_vec) = read_timecard(&args, &sheet, path, None)?;
+    let issues = export_timecard(&args, &sheet, path, date_range, employees_vec, &[])?;
+    Ok((sheet, issues))
+}
+
+fn first_sheet(path: &Path) -> anyhow::Result<String> {
+    calamine::open_workbook_auto(path)
+        .with_context(|| format!("could not open excel workbook at `{:?}`", path))?
+        .sheet_names()
+        .first()
+        .cloned()
+        .with_context(|| "the workbook has no worksheets")
+}
+
+fn serve_api(args: &Cli, address: &str, keep_hours: u64) -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("autotimecard-sage-{}", std::process::id()));
+    let keep = std::time::Duration::from_secs(keep_hours * 60 * 60);
+    let mut api = Api::bind(address, dir.clone(), keep)?;
+    if let Some(address) = api.address() {
+        info!(
+            "Listening on http://{}, keeping uploads in {:?}",
+            address, dir
+        );
+    }
+
+    api.run(|upload| convert_upload(args, upload))?;
+
+    Ok(())
+}
+
+/// Sets an option from an API request's parameter, named like the command line option.
+fn override_option(args: &mut Cli, name: &str, value: &str) -> anyhow::Result<()> {
+    let flag = || matches!(value, "" | "1" | "true" | "yes");
+    match name {
+        "sheet" | "filename" => {}
+        "timecard-id" => args.timecard_id = value.to_string(),
+        "timecard-desc" => args.timecard_desc = value.to_string(),
+        "job-column" => args.job_column = Some(value.to_string()),
+        "earning-column" => args.earning_column = Some(value.to_string()),
+        "rate-column" => args.rate_column = Some(value.to_string()),
+        "segment-columns" => args.segment_columns = value.split(',').map(String::from).collect(),
+        "override-rates" => args.override_rates = flag(),
+        "allow-inactive" => args.allow_inactive = flag(),
+        "summary" => args.summary = flag(),
+        _ => anyhow::bail!("`{}` is not an option that can be set per request", name),
+    }
+
+    Ok(())
+}
+
+/// Reads the options and the timecard of an upload, failing when the request or workbook
+/// is at fault.
+fn prepare_upload(
+    args: &Cli,
+    upload: &Upload,
+) -> anyhow::Result<(Cli, String, DateColumnRange, Vec<Employee>)> {
+    let mut args = args.clone();
+    for (name, value) in upload.params.iter() {
+        override_option(&mut args, name, value)?;
+    }
+    args.output = Some(upload.output.display().to_string());
+    args.dry_run = false;
+    args.emit_json = None;
+    args.approval_report = None;
+    args.cost_report = None;
+
+    let sheet = match upload.params.get("sheet") {
+        Some(sheet) => sheet.clone(),
+        None => first_sheet(&upload.workbook)?,
+    };
+    let (date_range, employees_vec) = read_timecard(&args, &sheet, &upload.workbook, None)?;
+
+    Ok((args, sheet, date_range, employees_vec))
+}
+
+/// Converts a workbook uploaded to the API. Reports and other files written next to the
+/// Sage import are left out, as the API has nowhere to return them.
+fn convert_upload(args: &Cli, upload: &Upload) -> Outcome {
+    let (args, sheet, date_range, employees_vec) = match prepare_upload(args, upload) {
+        Ok(prepared) => prepared,
+        Err(err) => return Outcome::Rejected(format!("{:#}", err)),
+    };
+
+    let path = &upload.workbook;
+    match export_timecard(&args, &sheet, path, date_range, employees_vec, &[]) {
+        Ok(issues) => Outcome::Exported(issues),
+        Err(err) => match err.downcast::<ValidationFailed>() {
+            Ok(ValidationFailed(issues)) => Outcome::Invalid(issues),
+            Err(err) => Outcome::Failed(format!("{:#}", err)),
+        },
+    }
+}
+
+/// Stops a run that exports employees already exported for the same period, unless
+/// duplicates are allowed, in which case they are only warned about.
+fn check_duplicates(history: &History, run: &Run, allow: bool) -> anyhow::Result<()> {
+    let duplicates = history.duplicates(run);
+    for duplicate in duplicates.iter() {
+        let message = format!(
+            "The period ending {} was already exported for employees {} in run {} at {}{}",
+            run.period_end,
+            duplicate.employees.join(", "),
+            duplicate.run,
+            duplicate.time,
+            if duplicate.same_input {
+                ", from the same workbook"
+            } else {
+                ""
+            }
+        );
+        if allow {
+            warn!("{}", message);
+        } else {
+            error!("{}", message);
+        }
+    }
+
+    if !allow && !duplicates.is_empty() {
+        anyhow::bail!(
+            "refusing to export the period ending {} twice, use --allow-duplicate to export it anyway",
+            run.period_end
+        );
+    }
+
+    Ok(())
+}
+
+fn show_history(file: &Path, number: Option<usize>) -> anyhow::Result<()> {
+    let history = History::load(file)
+        .with_context(|| format!("failed to load run history `{:?}`", file))?;
+
+    let number = match number {
+        Some(number) => number,
+        None => {
+            for (n, run) in history.runs.iter().enumerate() {
+                println!(
+                    "{}. {} `{}` period ending {}: {} employees, {:.2} hours -> {}{}",
+                    n + 1,
+                    run.time,
+                    run.sheet,
+                    run.period_end,
+                    run.employees.len(),
+                    run.total_hours,
+                    run.output,
+                    if run.correction { " (correction)" } else { "" }
+                );
+            }
+            return Ok(());
+        }
+    };
+
+    let run = history.get(number)?;
+    println!("Run {} at {}", number, run.time);
+    println!("Input:      {} (`{}`)", run.input, run.sheet);
+    println!("SHA-256:    {}", run.input_hash);
+    println!("Period end: {}", run.period_end);
+    println!("Output:     {}", run.output);
+    if run.correction {
+        println!("Correction of an earlier export");
+    }
+    for employee in run.employees.iter() {
+        println!("  {:<12} {:>8.2}", employee.id, employee.hours);
+    }
+    println!("  {:<12} {:>8.2}", "Total", run.total_hours);
+    if !run.edits.is_empty() {
+        println!("Edited on the review screen:");
+        for edit in run.edits.iter() {
+            println!("  {}", edit);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a timecard worksheet and applies every lookup file given on the command line.
+fn parse_timecard(
+    args: &Cli,
+    sheet: &str,
+    path: &Path,
+) -> anyhow::Result<(DateColumnRange, Vec<Employee>, Vec<Issue>)> {
+    let (date_range, mut employees_vec) = read_timecard(args, sheet, path, None)?;
+    let issues = apply_lookups(args, sheet, &date_range, &mut employees_vec)?;
+    Ok((date_range, employees_vec, issues))
+}
+
+/// Reads the dates and hours of a timecard worksheet, using `date_range` in place of the
+/// dates found on the worksheet when given.
+fn read_timecard(
+    args: &Cli,
+    sheet: &str,
+    path: &Path,
+    date_range: Option<DateColumnRange>,
+) -> anyhow::Result<(DateColumnRange, Vec<Employee>)> {
+    let workbook = &mut calamine::open_workbook_auto(path)
+        .with_context(|| format!("could not open excel workbook at `{:?}`", path))?;
+    trace!("opened excel workbook {:?}", path);
+
+    let date_range = match date_range {
+        Some(date_range) => date_range,
+        None => excel::timecards::parse_date_range(workbook, sheet)
+            .with_context(|| format!("failed to parse dates from workbook sheet `{}`", sheet))?,
+    };
+
+    let layout = timecard_layout(args, &date_range)?;
+    let employees_vec =
+        excel::timecards::parse_worksheet(workbook, sheet, date_range.clone(), &layout)
+            .with_context(|| {
+                format!(
+                    "failed to parse employee data from workbook sheet `{}`",
+                    sheet
+                )
+            })?;
+
+    Ok((date_range, employees_vec))
+}
+
+/// Shows the timecard on the review screen to have its dates and hours corrected. Returns
+/// the reviewed timecard without the employees left out, and the changes made to it.
+fn review_timecard(
+    args: &Cli,
+    sheet: &str,
+    path: &Path,
+) -> anyhow::Result<(DateColumnRange, Vec<Employee>, Vec<Edit>)> {
+    let (date_range, employees_vec) = read_timecard(args, sheet, path, None)?;
+    date_range
+        .range()
+        .with_context(|| format!("failed to parse dates from workbook sheet `{}`", sheet))?;
+
+    // Log lines would be written over the review screen.
+    let level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let reviewed = review::run(
+        Review::new(sheet, date_range, employees_vec),
+        |date_range| {
+            read_timecard(args, sheet, path, Some(date_range.clone()))
+                .map(|(_, employees)| employees)
+                .map_err(|err| format!("{:#}", err))
+        },
+        |date_range, employees| {
+            let mut employees = employees.to_vec();
+            apply_lookups(args, sheet, date_range, &mut employees)
+                .unwrap_or_else(|err| vec![Issue::error(None, format!("{:#}", err))])
+        },
     );
-    let is_dates_correct = dialoguer::Confirm::new()
-        .with_prompt("Do these dates look correct?")
-        .interact()?;
-    if !is_dates_correct {
-        println!(
-            "Please enter a date in the format {}",
-            chrono::Utc::now().format(DATE_FORMAT)
+    log::set_max_level(level);
+
+    let reviewed = reviewed
+        .with_context(|| "the review screen failed")?
+        .with_context(|| "the export was cancelled")?;
+    for edit in reviewed.edits.iter() {
+        info!("Review: {}", edit);
+    }
+
+    let included = reviewed.included();
+    Ok((reviewed.date_range, included, reviewed.edits))
+}
+
+/// Applies every lookup file given on the command line to a timecard's employees.
+fn apply_lookups(
+    args: &Cli,
+    sheet: &str,
+    date_range: &DateColumnRange,
+    employees_vec: &mut [Employee],
+) -> anyhow::Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+    if let Some(path) = &args.employees {
+        let master = EmployeeMaster::load(path)
+            .with_context(|| format!("failed to load employee master file `{:?}`", path))?;
+        issues.extend(master.apply(employees_vec));
+
+        let severity = if args.allow_inactive {
+            Severity::Warning
+        } else {
+            Severity::Error
+        };
+        issues.extend(master::check_employment(employees_vec, severity));
+    }
+    if let Some(path) = &args.job_rates {
+        let rates = JobRates::load(path)
+            .with_context(|| format!("failed to load job rate table `{:?}`", path))?;
+        issues.extend(rates.apply(employees_vec));
+    }
+    if args.override_rates {
+        for employee in employees_vec.iter_mut() {
+            employee.override_rate = true;
+        }
+    }
+    if let Some(path) = &args.dist_codes {
+        let dist_codes = DistributionCodes::load(path)
+            .with_context(|| format!("failed to load distribution code table `{:?}`", path))?;
+        issues.extend(dist_codes.apply(employees_vec));
+    }
+    if let Some(path) = &args.accrual_rules {
+        let rules = AccrualRules::load(path)
+            .with_context(|| format!("failed to load accrual rules `{:?}`", path))?;
+        issues.extend(rules.apply(employees_vec));
+    }
+    if let Some(path) = &args.shift_rules {
+        let rules = ShiftRules::load(path)
+            .with_context(|| format!("failed to load shift rules `{:?}`", path))?;
+        issues.extend(rules.apply(employees_vec));
+    } else {
+        // Shift rules report the codes they don't have themselves.
+        issues.extend(shifts::unknown_codes(employees_vec));
+    }
+    if let Some(calendar) = holiday_calendar(args, date_range)? {
+        let holiday_pay = HolidayPay {
+            worked_code: args.holiday_worked_code.clone(),
+            pay_code: args.holiday_pay_code.clone(),
+            eligibility_days: args.holiday_eligibility_days,
+        };
+        issues.extend(holiday_pay.apply(&calendar, date_range, employees_vec));
+    }
+    if let Some(path) = &args.segments {
+        let segments = SegmentList::load(path)
+            .with_context(|| format!("failed to load GL segment list `{:?}`", path))?;
+        issues.extend(segments.apply(employees_vec));
+    }
+    if let Some(path) = &args.accounts {
+        let chart = ChartOfAccounts::load(path)
+            .with_context(|| format!("failed to load chart of accounts `{:?}`", path))?;
+        issues.extend(chart.validate(employees_vec));
+    }
+    let template = TimecardTemplate {
+        id: args.timecard_id.clone(),
+        desc: args.timecard_desc.clone(),
+    };
+    issues.extend(template.apply(sheet, date_range, employees_vec));
+    Ok(issues)
+}
+
+fn diff_timecards(args: &Cli, sheet: &str, old: &Path, new: &Path) -> anyhow::Result<()> {
+    let mut periods = Vec::new();
+    for path in [old, new] {
+        let workbook = calamine::open_workbook_auto(path)
+            .with_context(|| format!("could not open excel workbook at `{:?}`", path))?;
+        let (date_range, mut employees, issues) = if is_sage_import(&workbook) {
+            let timecard = read_sage_import(path)
+                .and_then(|import| SageTimecard::from_import(&import))
+                .with_context(|| format!("failed to read Sage import workbook `{:?}`", path))?;
+            info!("read Sage timecard `{}` from {:?}", timecard.timecard, path);
+            (timecard.date_range(), timecard.employees, Vec::new())
+        } else {
+            parse_timecard(args, sheet, path)?
+        };
+        validation::report(&issues);
+
+        // Sage import workbooks only list employees with hours.
+        employees.retain(|e| sum_of_hours(e.hours.clone()) > 0.0);
+        periods.push(
+            PayPeriod::new(sheet, &date_range, &employees, &issues)
+                .with_context(|| format!("failed to read pay period from `{:?}`", path))?,
+        );
+    }
+
+    let changes = diff::diff(&periods[0], &periods[1]);
+    if changes.is_empty() {
+        println!("No changes");
+    }
+    for change in changes.iter() {
+        println!("{}", change);
+    }
+
+    Ok(())
+}
+
+fn timecard_layout(args: &Cli, date_range: &DateColumnRange) -> anyhow::Result<Layout> {
+    let mut layout = Layout::default();
+
+    if let Some(letter) = &args.job_column {
+        layout.job = Some(layout_column(letter, date_range)?);
+    }
+    if let Some(letter) = &args.earning_column {
+        layout.earning = Some(layout_column(letter, date_range)?);
+    }
+    if let Some(letter) = &args.rate_column {
+        layout.rate = Some(layout_column(letter, date_range)?);
+    }
+    if args.segment_columns.len() > layout.segments.len() {
+        anyhow::bail!("at most three GL segment columns can be given");
+    }
+    for (n, letter) in args.segment_columns.iter().enumerate() {
+        layout.segments[n] = Some(layout_column(letter, date_range)?);
+    }
+
+    Ok(layout)
+}
+
+fn holiday_calendar(
+    args: &Cli,
+    date_range: &DateColumnRange,
+) -> anyhow::Result<Option<HolidayCalendar>> {
+    let mut calendar = match &args.province {
+        Some(province) => {
+            let (start, end) = date_range
+                .range()
+                .with_context(|| "timecard has no date range")?;
+            let years: Vec<i32> = (start.year()..=end.year()).collect();
+            HolidayCalendar::for_province(province, &years)?
+        }
+        None => HolidayCalendar::default(),
+    };
+    if let Some(path) = &args.holidays {
+        calendar.extend(
+            HolidayCalendar::load(path)
+                .with_context(|| format!("failed to load holidays `{:?}`", path))?,
         );
+    }
+
+    if args.province.is_none() && args.holidays.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(calendar))
+}
+
+fn layout_column(letter: &str, date_range: &DateColumnRange) -> anyhow::Result<usize> {
+    let col = from_column_letter(letter.trim().to_uppercase());
+    if col < 4 {
+        anyhow::bail!(
+            "column `{}` holds the employee id, OT schedule, dist code or expense account",
+            letter
+        );
+    }
+    if date_range.in_range(col as usize) {
+        anyhow::bail!("column `{}` overlaps the date columns", letter);
+    }
 
-        let start_input: String = dialoguer::Input::new()
-            .with_prompt("What is the start date?")
-            .interact_text()?;
-        let start_naive = chrono::NaiveDate::parse_from_str(&start_input, DATE_FORMAT)
-            .with_context(|| "please enter date in the format: January 01, 2021")?;
-        let start_date = chrono::Date::<chrono::Utc>::from_utc(start_naive, chrono::Utc);
-        date_range.start = Some(start_date);
-    }
-
-    let are_columns_correct = dialoguer::Confirm::new()
-        .with_prompt(format!(
-            "Do the dates range from columns `{}` to `{}`",
-            to_column_letter(date_range.head as i32),
-            to_column_letter(date_range.tail as i32)
-        ))
-        .interact()?;
-    if !are_columns_correct {
-        let start_column_letter: String = dialoguer::Input::new()
-            .with_prompt("What column does the start date appear on?")
-            .interact_text()?;
-        date_range.head = from_column_letter(start_column_letter) as usize;
-        let end_column_letter: String = dialoguer::Input::new()
-            .with_prompt("What column does the end date appear on?")
-            .interact_text()?;
-        date_range.tail = from_column_letter(end_column_letter) as usize;
-    }
-
-    date_range.end =
-        Some(date_range.start.unwrap() + chrono::Duration::days(date_range.len() as i64));
-
-    if !is_dates_correct || !are_columns_correct {
-        return date_range_correct_confirmation(args, date_range);
-    }
-
-    Ok(date_range)
+    Ok(col as usize)
 }
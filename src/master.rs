@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{Date, Utc};
+
+use crate::employees::{sum_of_hours, Employee, Shift};
+use crate::excel::records::{read_records, Record};
+use crate::excel::ExcelError;
+use crate::validation::{Issue, Severity};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// An employee's standing details, as exported from Sage or maintained by payroll.
+///
+/// Columns use the Sage field names (`EMPLOYEE`, `LASTNAME`, `HIREDATE`, ...).
+#[derive(Debug, Clone, Default)]
+pub struct MasterRecord {
+    pub id: String,
+    pub last_name: String,
+    pub first_name: String,
+    pub hire_date: Option<Date<Utc>>,
+    pub pay_frequency: String,
+    pub part_time: String,
+    pub shift_schedule: String,
+    pub overtime_schedule: String,
+    pub dist_code: String,
+    pub exp_account: String,
+    pub ot_account: String,
+    pub liab_account: String,
+    pub shift_account: String,
+    /// Default `GLSEG1` to `GLSEG3` values for rows that don't specify their own.
+    pub segments: [String; 3],
+    pub status: String,
+    pub inactive_date: Option<Date<Utc>>,
+    pub fire_date: Option<Date<Utc>>,
+    /// Default hourly rate (`RATE`), and whether it overrides Sage's rate (`RRATEOVER`).
+    pub rate: Option<f64>,
+    pub override_rate: bool,
+}
+
+impl MasterRecord {
+    fn from_record(record: &Record) -> Result<Option<Self>, ExcelError> {
+        let id = match record.get("EMPLOYEE") {
+            Some(id) => id.to_string(),
+            None => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            hire_date: record.get_date("HIREDATE")?,
+            inactive_date: record.get_date("INACTDATE")?,
+            fire_date: record.get_date("FIREDATE")?,
+            last_name: record.get_string("LASTNAME"),
+            first_name: record.get_string("FIRSTNAME"),
+            pay_frequency: record.get_string("PAYFREQ"),
+            part_time: record.get_string("PARTTIME"),
+            shift_schedule: record.get_string("SHIFTSCHED"),
+            overtime_schedule: record.get_string("OTSCHED"),
+            dist_code: record.get_string("DISTCODE"),
+            exp_account: record.get_string("EXPACCT"),
+            ot_account: record.get_string("OTACCT"),
+            liab_account: record.get_string("LIABACCT"),
+            shift_account: record.get_string("SHIFTACCT"),
+            segments: [
+                record.get_string("GLSEG1"),
+                record.get_string("GLSEG2"),
+                record.get_string("GLSEG3"),
+            ],
+            status: record.get_string("STATUS"),
+            rate: record.get_number("RATE")?,
+            override_rate: is_yes(record.get("RRATEOVER").unwrap_or_default()),
+            id,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EmployeeMaster {
+    records: HashMap<String, MasterRecord>,
+}
+
+impl EmployeeMaster {
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut master = Self::default();
+        for record in records.iter() {
+            if let Some(record) = MasterRecord::from_record(record)? {
+                master.records.insert(record.id.clone(), record);
+            }
+        }
+
+        Ok(master)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&MasterRecord> {
+        self.records.get(id)
+    }
+
+    /// Fills each employee's details from the master file, using the master's OT
+    /// schedule, dist code, GL accounts, GL segments and rate wherever the timecard row
+    /// left them blank.
+    ///
+    /// Employees with hours who are missing from the master are flagged.
+    pub fn apply(&self, employees: &mut [Employee]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for employee in employees.iter_mut() {
+            let record = match self.get(&employee.id) {
+                Some(record) => record,
+                None => {
+                    if sum_of_hours(employee.hours.clone()) > 0.0 {
+                        issues.push(Issue::warning(
+                            Some(&employee.id),
+                            "not found in the employee master file".to_string(),
+                        ));
+                    }
+                    continue;
+                }
+            };
+
+            employee.last_name = record.last_name.clone();
+            employee.first_name = record.first_name.clone();
+            employee.hire_date = record.hire_date;
+            employee.pay_frequency = record.pay_frequency.clone();
+            employee.part_time = record.part_time.clone();
+            employee.shift_schedule = record.shift_schedule.clone();
+            employee.status = record.status.clone();
+            employee.inactive_date = record.inactive_date;
+            employee.fire_date = record.fire_date;
+            if employee.rate.is_none() {
+                employee.rate = record.rate;
+            }
+            employee.override_rate |= record.override_rate;
+
+            if employee.overtime_schedule.is_empty() {
+                employee.overtime_schedule = record.overtime_schedule.clone();
+            }
+            if employee.dist_code.is_empty() {
+                employee.dist_code = record.dist_code.clone();
+            }
+            if employee.exp_account.is_empty() {
+                employee.exp_account = record.exp_account.clone();
+            }
+            if employee.ot_account.is_empty() {
+                employee.ot_account = record.ot_account.clone();
+            }
+            if employee.liab_account.is_empty() {
+                employee.liab_account = record.liab_account.clone();
+            }
+            if employee.shift_account.is_empty() {
+                employee.shift_account = record.shift_account.clone();
+            }
+            for shift in employee.hours.iter_mut() {
+                for (segment, default) in shift.segments.iter_mut().zip(record.segments.iter()) {
+                    if segment.value.is_empty() {
+                        segment.value = default.clone();
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+fn is_yes(value: &str) -> bool {
+    ["1", "y", "yes", "true"]
+        .iter()
+        .any(|yes| value.eq_ignore_ascii_case(yes))
+}
+
+/// Sage marks inactive employees with a `STATUS` of 0; exports from other tools spell it out.
+fn is_inactive_status(status: &str) -> bool {
+    status == "0"
+        || status.eq_ignore_ascii_case("inactive")
+        || status.eq_ignore_ascii_case("terminated")
+}
+
+fn format_dates(shifts: &[&Shift]) -> String {
+    shifts
+        .iter()
+        .map(|shift| shift.date.format(DATE_FORMAT).to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Flags shifts worked before an employee's hire date or after their termination or
+/// inactive date, plus employees whose status is inactive but who still have hours.
+///
+/// These are almost always stale rows copied forward in the timecard template, so they
+/// are reported with `severity` (an error unless the caller chooses otherwise).
+pub fn check_employment(employees: &[Employee], severity: Severity) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for employee in employees.iter() {
+        let worked: Vec<&Shift> = employee
+            .hours
+            .iter()
+            .filter(|shift| shift.sum_of_shift() > 0.0)
+            .collect();
+        if worked.is_empty() {
+            continue;
+        }
+
+        if let Some(hire_date) = employee.hire_date {
+            let early: Vec<&Shift> = worked
+                .iter()
+                .copied()
+                .filter(|shift| shift.date < hire_date)
+                .collect();
+            if !early.is_empty() {
+                issues.push(Issue::new(
+                    severity,
+                    Some(&employee.id),
+                    format!(
+                        "has hours on {} before their hire date {}",
+                        format_dates(&early),
+                        hire_date.format(DATE_FORMAT)
+                    ),
+                ));
+            }
+        }
+
+        for (label, date) in [
+            ("termination", employee.fire_date),
+            ("inactive", employee.inactive_date),
+        ] {
+            let date = match date {
+                Some(date) => date,
+                None => continue,
+            };
+            let late: Vec<&Shift> = worked
+                .iter()
+                .copied()
+                .filter(|shift| shift.date > date)
+                .collect();
+            if !late.is_empty() {
+                issues.push(Issue::new(
+                    severity,
+                    Some(&employee.id),
+                    format!(
+                        "has hours on {} after their {} date {}",
+                        format_dates(&late),
+                        label,
+                        date.format(DATE_FORMAT)
+                    ),
+                ));
+            }
+        }
+
+        if is_inactive_status(&employee.status)
+            && employee.fire_date.is_none()
+            && employee.inactive_date.is_none()
+        {
+            issues.push(Issue::new(
+                severity,
+                Some(&employee.id),
+                "has hours but is inactive in the employee master file".to_string(),
+            ));
+        }
+    }
+
+    issues
+}
+
+#[test]
+fn it_fills_blanks_from_the_master() {
+    use crate::excel::records::{employee_with, records_of, shift_on};
+
+    let rows = [
+        ["EMPLOYEE", "LASTNAME", "OTSCHED", "DISTCODE", "RATE"],
+        ["1001", "Barry", "STD", "OFFICE", "24.50"],
+    ];
+    let master = EmployeeMaster::from_records(records_of(&rows)).unwrap();
+
+    let mut known = Employee::new("1001".to_string());
+    known.dist_code = "SHOP".to_string();
+    let unknown = employee_with("2002", vec![shift_on(0, 8)]);
+    let mut employees = vec![known, unknown];
+
+    let issues = master.apply(&mut employees);
+
+    assert_eq!(employees[0].last_name, "Barry");
+    assert_eq!(employees[0].overtime_schedule, "STD");
+    assert_eq!(employees[0].dist_code, "SHOP");
+    assert_eq!(employees[0].rate, Some(24.5));
+    assert!(!employees[0].override_rate);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].employee.as_deref(), Some("2002"));
+}
+
+#[test]
+fn it_flags_hours_outside_employment() {
+    use crate::excel::records::{employee_with, shift_on, test_start};
+
+    let start = test_start();
+    let mut employee = employee_with("1001", (0..4).map(|day| shift_on(day, 8)).collect());
+    employee.hire_date = Some(start + chrono::Duration::days(1));
+    employee.fire_date = Some(start + chrono::Duration::days(2));
+
+    let issues = check_employment(&[employee], Severity::Error);
+
+    assert_eq!(issues.len(), 2);
+    assert!(issues.iter().all(|issue| issue.is_error()));
+    assert!(issues[0].message.contains("before their hire date"));
+    assert!(issues[1].message.contains("after their termination date"));
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+use crate::excel::sage::{SageImport, SageRow};
+
+/// How `--dry-run` prints the rows that would be written to the Sage import workbook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format `{}`, expected table or json", s)),
+        }
+    }
+}
+
+pub fn print(import: &SageImport, format: Format) -> Result<(), serde_json::Error> {
+    match format {
+        Format::Table => {
+            println!("Timecard_Header");
+            print!("{}", format_table(&import.header));
+            println!();
+            println!("Timecard_Detail");
+            print!("{}", format_table(&import.detail));
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(import)?),
+    }
+
+    Ok(())
+}
+
+/// Lays rows out as aligned columns, leaving out the columns no row has a value for so
+/// that the table fits in a terminal.
+pub fn format_table(rows: &[SageRow]) -> String {
+    let cells: Vec<Vec<(&str, Option<&str>)>> =
+        rows.iter().map(|row| row.cells().collect()).collect();
+    let first = match cells.first() {
+        Some(first) => first,
+        None => return String::from("(no rows)\n"),
+    };
+
+    // (column index, heading, width)
+    let columns: Vec<(usize, &str, usize)> = first
+        .iter()
+        .enumerate()
+        .filter(|(col, _)| cells.iter().any(|row| row[*col].1.is_some()))
+        .map(|(col, (heading, _))| {
+            let width = cells
+                .iter()
+                .map(|row| row[col].1.unwrap_or_default().len())
+                .chain(std::iter::once(heading.len()))
+                .max()
+                .unwrap_or_default();
+            (col, *heading, width)
+        })
+        .collect();
+
+    let mut table = String::new();
+    let mut push_line = |values: Vec<&str>| {
+        let line: Vec<String> = values
+            .iter()
+            .zip(columns.iter())
+            .map(|(value, (_, _, width))| format!("{:<width$}", value, width = width))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    };
+
+    push_line(columns.iter().map(|(_, heading, _)| *heading).collect());
+    let rules: Vec<String> = columns
+        .iter()
+        .map(|(_, _, width)| "-".repeat(*width))
+        .collect();
+    push_line(rules.iter().map(String::as_str).collect());
+    for row in cells.iter() {
+        push_line(
+            columns
+                .iter()
+                .map(|(col, _, _)| row[*col].1.unwrap_or_default())
+                .collect(),
+        );
+    }
+
+    table
+}
+
+#[test]
+fn it_formats_only_used_columns() {
+    use crate::excel::sage::TIMECARD_HEADER_HEADERS;
+
+    let mut first = SageRow::new(TIMECARD_HEADER_HEADERS);
+    first.set("EMPLOYEE", "1001");
+    first.set("TIMECARD", "SEMI");
+    let mut second = SageRow::new(TIMECARD_HEADER_HEADERS);
+    second.set("EMPLOYEE", "20002");
+
+    assert_eq!(
+        format_table(&[first, second]),
+        "EMPLOYEE  TIMECARD\n--------  --------\n1001      SEMI\n20002\n"
+    );
+}
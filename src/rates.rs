@@ -0,0 +1,123 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::employees::Employee;
+use crate::excel::records::{read_records, Record};
+use crate::excel::ExcelError;
+use crate::validation::Issue;
+
+/// Hourly rates by job, for clients that pay the same employee differently on different
+/// jobs.
+///
+/// Each row has a `JOB` and `RATE`, and optionally an `EMPLOYEE` when the rate only applies
+/// to that employee. Employee rates take precedence over a job's rate for everyone.
+#[derive(Debug, Clone, Default)]
+pub struct JobRates {
+    rates: HashMap<(String, String), f64>,
+}
+
+impl JobRates {
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut table = Self::default();
+
+        for record in records.iter() {
+            let job = match record.get("JOB") {
+                Some(job) => job.to_string(),
+                None => continue,
+            };
+            let rate = record.get_number("RATE")?.ok_or_else(|| {
+                ExcelError::Msg(format!("job rate table has no `RATE` for job `{}`", job))
+            })?;
+            table
+                .rates
+                .insert((record.get_string("EMPLOYEE"), job), rate);
+        }
+
+        Ok(table)
+    }
+
+    fn get(&self, employee: &str, job: &str) -> Option<f64> {
+        self.rates
+            .get(&(employee.to_string(), job.to_string()))
+            .or_else(|| self.rates.get(&(String::new(), job.to_string())))
+            .copied()
+    }
+
+    /// Sets the rate of every job costed shift that didn't have one on its timecard row,
+    /// reporting jobs that still have no rate for an employee without a rate of their own.
+    pub fn apply(&self, employees: &mut [Employee]) -> Vec<Issue> {
+        let mut missing: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+        for employee in employees.iter_mut() {
+            for shift in employee.hours.iter_mut() {
+                if shift.job.is_empty() || shift.rate.is_some() {
+                    continue;
+                }
+
+                shift.rate = self.get(&employee.id, &shift.job);
+                if shift.rate.is_none() && employee.rate.is_none() && shift.sum_of_shift() > 0.0 {
+                    missing
+                        .entry(shift.job.clone())
+                        .or_default()
+                        .insert(employee.id.clone());
+                }
+            }
+        }
+
+        missing
+            .into_iter()
+            .map(|(job, ids)| {
+                Issue::warning(
+                    None,
+                    format!(
+                        "job `{}` has no rate in the job rate table (employees {})",
+                        job,
+                        ids.into_iter().collect::<Vec<String>>().join(", ")
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn it_prefers_employee_job_rates() {
+    use crate::excel::records::{employee_with, records_of, shift_on};
+
+    let rows = [
+        ["EMPLOYEE", "JOB", "RATE"],
+        ["", "A-100", "22.50"],
+        ["1002", "A-100", "30"],
+        ["", "B-200", "25"],
+    ];
+    let rates = JobRates::from_records(records_of(&rows)).unwrap();
+
+    let employee = |id: &str, jobs: &[&str]| {
+        let shifts = jobs.iter().map(|job| {
+            let mut shift = shift_on(0, 8);
+            shift.job = job.to_string();
+            shift
+        });
+        employee_with(id, shifts.collect())
+    };
+    let mut typed = employee("1001", &["A-100"]);
+    typed.hours[0].rate = Some(19.0);
+    let mut employees = vec![
+        typed,
+        employee("1002", &["A-100", "B-200", "C-300"]),
+        employee("1003", &["C-300"]),
+    ];
+
+    let issues = rates.apply(&mut employees);
+
+    assert_eq!(employees[0].hours[0].rate, Some(19.0));
+    let rates: Vec<Option<f64>> = employees[1].hours.iter().map(|s| s.rate).collect();
+    assert_eq!(rates, vec![Some(30.0), Some(25.0), None]);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("`C-300`"));
+    assert!(issues[0].message.contains("(employees 1002, 1003)"));
+}
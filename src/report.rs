@@ -0,0 +1,159 @@
+use std::fmt::{self, Write};
+
+use crate::summary::{Summary, SummaryRow};
+
+const DAY_FORMAT: &str = "%a<br>%m/%d";
+const DATE_FORMAT: &str = "%B %d, %Y";
+
+const STYLE: &str = "
+body { font-family: sans-serif; font-size: 10pt; margin: 1.5em; }
+h1 { font-size: 14pt; margin-bottom: 0; }
+h2 { font-size: 12pt; margin: 1.5em 0 0.5em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #999; padding: 2px 4px; }
+th { background: #ddd; }
+td.hours { text-align: right; }
+tr.total td { font-weight: bold; border-top: 2px solid #000; }
+.signature { margin-top: 2.5em; display: flex; gap: 3em; }
+.signature div { flex: 1; border-top: 1px solid #000; padding-top: 2px; }
+section { page-break-after: always; }
+section:last-child { page-break-after: auto; }
+@media print { body { margin: 0; } }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hours(value: f32) -> String {
+    if value > 0.0 {
+        format!("{:.2}", value)
+    } else {
+        String::new()
+    }
+}
+
+/// Renders an HTML page for managers to approve hours before they're imported, with one
+/// printable section and signature block per distribution code.
+pub fn approval_report(title: &str, summary: &Summary) -> Result<String, fmt::Error> {
+    let mut groups: Vec<(&str, Vec<&SummaryRow>)> = Vec::new();
+    for row in summary.rows.iter() {
+        match groups.iter_mut().find(|(code, _)| *code == row.dist_code) {
+            Some((_, rows)) => rows.push(row),
+            None => groups.push((&row.dist_code, vec![row])),
+        }
+    }
+    groups.sort_by_key(|(code, _)| *code);
+
+    let period = match (summary.days.first(), summary.days.last()) {
+        (Some(first), Some(last)) => format!(
+            "{} to {}",
+            first.format(DATE_FORMAT),
+            last.format(DATE_FORMAT)
+        ),
+        _ => String::new(),
+    };
+
+    let mut html = String::new();
+    write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(title),
+        STYLE
+    )?;
+
+    for (code, rows) in groups.iter() {
+        let code = if code.is_empty() {
+            "No distribution code".to_string()
+        } else {
+            format!("Distribution code {}", escape(code))
+        };
+        writeln!(html, "<section>")?;
+        writeln!(html, "<h1>{}</h1>\n<p>{}</p>", escape(title), period)?;
+        writeln!(html, "<h2>{}</h2>", code)?;
+
+        write!(html, "<table>\n<tr><th>Employee</th><th>Name</th>")?;
+        for day in summary.days.iter() {
+            write!(html, "<th>{}</th>", day.format(DAY_FORMAT))?;
+        }
+        writeln!(html, "<th>Regular</th><th>Overtime</th><th>Total</th></tr>")?;
+
+        for row in rows.iter() {
+            write!(
+                html,
+                "<tr><td>{}</td><td>{}</td>",
+                escape(&row.id),
+                escape(&row.name)
+            )?;
+            for value in row.daily.iter() {
+                write!(html, "<td class=\"hours\">{}</td>", hours(*value))?;
+            }
+            writeln!(
+                html,
+                "<td class=\"hours\">{}</td><td class=\"hours\">{}</td><td class=\"hours\">{}</td></tr>",
+                hours(row.regular),
+                hours(row.overtime),
+                hours(row.total())
+            )?;
+        }
+
+        write!(html, "<tr class=\"total\"><td colspan=\"2\">Total</td>")?;
+        for n in 0..summary.days.len() {
+            let total: f32 = rows.iter().map(|row| row.daily[n]).sum();
+            write!(html, "<td class=\"hours\">{}</td>", hours(total))?;
+        }
+        let regular: f32 = rows.iter().map(|row| row.regular).sum();
+        let overtime: f32 = rows.iter().map(|row| row.overtime).sum();
+        writeln!(
+            html,
+            "<td class=\"hours\">{}</td><td class=\"hours\">{}</td><td class=\"hours\">{}</td></tr>\n</table>",
+            hours(regular),
+            hours(overtime),
+            hours(regular + overtime)
+        )?;
+
+        writeln!(
+            html,
+            "<div class=\"signature\"><div>Approved by</div><div>Signature</div><div>Date</div></div>\n</section>"
+        )?;
+    }
+
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+#[test]
+fn it_groups_the_report_by_dist_code() {
+    use chrono::{Duration, TimeZone, Utc};
+
+    let row = |id: &str, dist_code: &str, daily: Vec<f32>| SummaryRow {
+        id: id.to_string(),
+        name: "O'Brien & Sons <temp>".to_string(),
+        dist_code: dist_code.to_string(),
+        exp_account: String::new(),
+        ot_account: String::new(),
+        regular: daily.iter().sum(),
+        overtime: 0.0,
+        daily,
+    };
+    let summary = Summary {
+        days: vec![Utc.ymd(2021, 5, 3), Utc.ymd(2021, 5, 3) + Duration::days(1)],
+        rows: vec![
+            row("1001", "SHOP", vec![8.0, 0.0]),
+            row("1002", "OFFICE", vec![4.0, 4.0]),
+            row("1003", "SHOP", vec![0.0, 7.5]),
+        ],
+    };
+
+    let html = approval_report("Week 1", &summary).unwrap();
+    assert_eq!(html.matches("<section>").count(), 2);
+    assert!(
+        html.find("Distribution code OFFICE").unwrap()
+            < html.find("Distribution code SHOP").unwrap()
+    );
+    assert!(html.contains("O'Brien &amp; Sons &lt;temp&gt;"));
+    assert!(html.contains("<td class=\"hours\">15.50</td></tr>\n</table>"));
+}
@@ -0,0 +1,745 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+
+use chrono::{Date, Duration, NaiveDate, Utc};
+use crossterm::cursor::Show;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table, TableState};
+use tui::{Frame, Terminal};
+
+use crate::employees::{sum_of_hours, Employee, Shift};
+use crate::excel::timecards::DateColumnRange;
+use crate::excel::{from_column_letter, to_column_letter};
+use crate::validation::{Issue, Severity};
+use crate::DATE_FORMAT;
+
+/// A change made to the timecard while reviewing it, kept in the run history for audit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Dates {
+        start: Date<Utc>,
+        head: usize,
+        tail: usize,
+    },
+    Excluded(String),
+    Included(String),
+    Hours {
+        employee: String,
+        date: Date<Utc>,
+        from: f32,
+        to: f32,
+    },
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edit::Dates { start, head, tail } => write!(
+                f,
+                "dates start {} in columns `{}` to `{}`",
+                start.format("%Y-%m-%d"),
+                to_column_letter(*head as i32),
+                to_column_letter(*tail as i32)
+            ),
+            Edit::Excluded(id) => write!(f, "excluded employee `{}`", id),
+            Edit::Included(id) => write!(f, "included employee `{}` again", id),
+            Edit::Hours {
+                employee,
+                date,
+                from,
+                to,
+            } => write!(
+                f,
+                "changed employee `{}` on {} from {:.2} to {:.2} hours",
+                employee,
+                date.format("%Y-%m-%d"),
+                from,
+                to
+            ),
+        }
+    }
+}
+
+/// A timecard's dates and hours as read from the worksheet, before any lookup file is
+/// applied, with the changes made to them while reviewing.
+#[derive(Clone)]
+pub struct Review {
+    pub sheet: String,
+    pub date_range: DateColumnRange,
+    pub employees: Vec<Employee>,
+    pub excluded: BTreeSet<String>,
+    pub edits: Vec<Edit>,
+    /// The issues found the last time the timecard was validated.
+    pub issues: Vec<Issue>,
+}
+
+impl Review {
+    pub fn new(sheet: &str, date_range: DateColumnRange, employees: Vec<Employee>) -> Self {
+        Self {
+            sheet: sheet.to_string(),
+            date_range,
+            employees,
+            excluded: BTreeSet::new(),
+            edits: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    pub fn days(&self) -> Vec<Date<Utc>> {
+        self.date_range.clone().collect()
+    }
+
+    /// The employees that haven't been excluded.
+    pub fn included(&self) -> Vec<Employee> {
+        self.employees
+            .iter()
+            .filter(|employee| !self.excluded.contains(&employee.id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn hours_on(&self, row: usize, date: Date<Utc>) -> f32 {
+        self.employees[row]
+            .hours
+            .iter()
+            .filter(|shift| shift.date == date)
+            .fold(0.0, |hours, shift| hours + shift.sum_of_shift())
+    }
+
+    fn column_of(&self, date: Date<Utc>) -> Option<usize> {
+        let (start, _) = self.date_range.range()?;
+        let col = self.date_range.head as i64 + (date - start).num_days();
+        if col < 0 || !self.date_range.in_range(col as usize) {
+            return None;
+        }
+
+        Some(col as usize)
+    }
+
+    /// Sets an employee's hours for a day, returning what they were. A day without hours
+    /// gets regular hours with the job, GL segments and rate of the employee's first line;
+    /// days split across several lines have to be changed in the workbook.
+    fn change_hours(&mut self, row: usize, date: Date<Utc>, hours: f32) -> Result<f32, String> {
+        if !(0.0..=24.0).contains(&hours) {
+            return Err(format!("{} is not a number of hours in a day", hours));
+        }
+        let col = self.column_of(date).ok_or_else(|| {
+            format!(
+                "{} is not one of the timecard's dates",
+                date.format(DATE_FORMAT)
+            )
+        })?;
+        let from = self.hours_on(row, date);
+
+        let employee = &mut self.employees[row];
+        let lines: Vec<usize> = employee
+            .hours
+            .iter()
+            .enumerate()
+            .filter(|(_, shift)| shift.date == date)
+            .map(|(n, _)| n)
+            .collect();
+        let duration = Duration::minutes((hours * 60.0).round() as i64);
+        match lines.as_slice() {
+            [] if hours > 0.0 => {
+                let mut shift = Shift::new(col, duration, date);
+                if let Some(first) = employee.hours.first() {
+                    shift.job = first.job.clone();
+                    shift.segments = first.segments.clone();
+                    shift.rate = first.rate;
+                }
+                employee.hours.push(shift);
+                employee.hours.sort_by_key(|shift| shift.date);
+            }
+            [] => {}
+            [n] if hours > 0.0 => {
+                let shift = &mut employee.hours[*n];
+                shift.duration = duration;
+                shift.start = None;
+                shift.stop = None;
+            }
+            [n] => {
+                employee.hours.remove(*n);
+            }
+            _ => {
+                return Err(format!(
+                    "employee `{}` has {} lines on {}, change them in the workbook",
+                    employee.id,
+                    lines.len(),
+                    date.format(DATE_FORMAT)
+                ))
+            }
+        }
+
+        Ok(from)
+    }
+
+    pub fn set_hours(&mut self, row: usize, date: Date<Utc>, hours: f32) -> Result<(), String> {
+        if (self.hours_on(row, date) - hours).abs() < 0.005 {
+            return Ok(());
+        }
+
+        let from = self.change_hours(row, date, hours)?;
+        self.edits.push(Edit::Hours {
+            employee: self.employees[row].id.clone(),
+            date,
+            from,
+            to: hours,
+        });
+
+        Ok(())
+    }
+
+    pub fn toggle_excluded(&mut self, row: usize) {
+        let id = self.employees[row].id.clone();
+        if self.excluded.remove(&id) {
+            self.edits.push(Edit::Included(id));
+        } else {
+            self.excluded.insert(id.clone());
+            self.edits.push(Edit::Excluded(id));
+        }
+    }
+
+    /// Replaces the timecard with one read again for other dates, carrying over the hours
+    /// already changed on days that are still in the timecard. Changes that can't be made
+    /// again are dropped from the edits, and returned with the reason.
+    pub fn set_dates(
+        &mut self,
+        date_range: DateColumnRange,
+        employees: Vec<Employee>,
+    ) -> Vec<String> {
+        self.date_range = date_range;
+        self.employees = employees;
+
+        let mut dropped = Vec::new();
+        for edit in std::mem::take(&mut self.edits) {
+            if let Edit::Hours {
+                employee, date, to, ..
+            } = &edit
+            {
+                let changed = match self.employees.iter().position(|e| &e.id == employee) {
+                    Some(row) => self.change_hours(row, *date, *to),
+                    None => Err(format!(
+                        "employee `{}` is no longer in the timecard",
+                        employee
+                    )),
+                };
+                if let Err(message) = changed {
+                    dropped.push(format!("dropped \"{}\": {}", edit, message));
+                    continue;
+                }
+            }
+            self.edits.push(edit);
+        }
+
+        if let Some((start, _)) = self.date_range.range() {
+            self.edits.push(Edit::Dates {
+                start,
+                head: self.date_range.head,
+                tail: self.date_range.tail,
+            });
+        }
+
+        dropped
+    }
+
+    fn error_count(&self) -> usize {
+        self.issues.iter().filter(|issue| issue.is_error()).count()
+    }
+}
+
+/// The date range with its dates starting on `start` in columns `head` to `tail`.
+fn moved_dates(
+    date_range: &DateColumnRange,
+    start: Date<Utc>,
+    head: usize,
+    tail: usize,
+) -> DateColumnRange {
+    let mut moved = date_range.clone();
+    moved.start = Some(start);
+    moved.head = head;
+    moved.tail = tail;
+    moved.end = Some(start + Duration::days(moved.len() as i64));
+    moved
+}
+
+fn parse_date(value: &str) -> Option<Date<Utc>> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, DATE_FORMAT))
+        .ok()
+        .map(|date| Date::<Utc>::from_utc(date, Utc))
+}
+
+/// Parses columns given as `E:K` or `E-K`.
+fn parse_columns(value: &str) -> Option<(usize, usize)> {
+    let (head, tail) = value.split_once([':', '-'])?;
+    let head = from_column_letter(head.trim().to_uppercase());
+    let tail = from_column_letter(tail.trim().to_uppercase());
+    if head < 0 || tail < head {
+        return None;
+    }
+
+    Some((head as usize, tail as usize))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Prompt {
+    Hours,
+    Start,
+    Columns,
+}
+
+struct Screen {
+    row: usize,
+    day: usize,
+    prompt: Option<(Prompt, String)>,
+    status: String,
+    table: TableState,
+}
+
+/// Shows the timecard full screen for it to be checked and corrected before it is
+/// exported. `reparse` reads the worksheet again when the dates are changed and `validate`
+/// checks the employees after every change. Returns `None` when the export is cancelled.
+pub fn run(
+    mut review: Review,
+    mut reparse: impl FnMut(&DateColumnRange) -> Result<Vec<Employee>, String>,
+    mut validate: impl FnMut(&DateColumnRange, &[Employee]) -> Vec<Issue>,
+) -> io::Result<Option<Review>> {
+    review.issues = validate(&review.date_range, &review.included());
+
+    let restore = RawScreen::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let result = review_loop(&mut terminal, &mut review, &mut reparse, &mut validate);
+
+    drop(terminal);
+    drop(restore);
+
+    Ok(result?.then_some(review))
+}
+
+/// Puts the terminal in raw mode on the alternate screen, and puts it back when dropped so
+/// an error or panic while reviewing doesn't leave the shell unusable.
+struct RawScreen;
+
+impl RawScreen {
+    fn enter() -> io::Result<Self> {
+        enable_raw_mode()?;
+        let restore = RawScreen;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(restore)
+    }
+}
+
+impl Drop for RawScreen {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+    }
+}
+
+fn review_loop<B: Backend>(
+    terminal: &mut Terminal<B>,
+    review: &mut Review,
+    reparse: &mut impl FnMut(&DateColumnRange) -> Result<Vec<Employee>, String>,
+    validate: &mut impl FnMut(&DateColumnRange, &[Employee]) -> Vec<Issue>,
+) -> io::Result<bool> {
+    let mut screen = Screen {
+        row: 0,
+        day: 0,
+        prompt: None,
+        status: String::new(),
+        table: TableState::default(),
+    };
+
+    loop {
+        terminal.draw(|f| draw(f, review, &mut screen))?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => continue,
+        };
+
+        if let Some((prompt, input)) = &mut screen.prompt {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Esc => screen.prompt = None,
+                KeyCode::Enter => {
+                    let (prompt, input) = (*prompt, input.clone());
+                    screen.prompt = None;
+                    screen.status = match answer(review, &screen, prompt, &input, reparse) {
+                        Ok(status) => {
+                            review.issues = validate(&review.date_range, &review.included());
+                            status
+                        }
+                        Err(message) => message,
+                    };
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let rows = review.employees.len();
+        let days = review.days().len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Up | KeyCode::Char('k') => screen.row = screen.row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                screen.row = (screen.row + 1).min(rows.saturating_sub(1))
+            }
+            KeyCode::Left | KeyCode::Char('h') => screen.day = screen.day.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => {
+                screen.day = (screen.day + 1).min(days.saturating_sub(1))
+            }
+            KeyCode::Enter if rows > 0 && days > 0 => {
+                let hours = review.hours_on(screen.row, review.days()[screen.day]);
+                screen.prompt = Some((Prompt::Hours, format!("{}", hours)));
+            }
+            KeyCode::Char(c) if (c.is_ascii_digit() || c == '.') && rows > 0 && days > 0 => {
+                screen.prompt = Some((Prompt::Hours, c.to_string()));
+            }
+            KeyCode::Char('x') | KeyCode::Char(' ') if rows > 0 => {
+                review.toggle_excluded(screen.row);
+                review.issues = validate(&review.date_range, &review.included());
+                screen.status = review.edits.last().map(Edit::to_string).unwrap_or_default();
+            }
+            KeyCode::Char('d') => {
+                let start = review
+                    .date_range
+                    .start
+                    .map(|start| start.format("%Y-%m-%d"));
+                screen.prompt = Some((
+                    Prompt::Start,
+                    start.map(|start| start.to_string()).unwrap_or_default(),
+                ));
+            }
+            KeyCode::Char('c') => {
+                let columns = format!(
+                    "{}:{}",
+                    to_column_letter(review.date_range.head as i32),
+                    to_column_letter(review.date_range.tail as i32)
+                );
+                screen.prompt = Some((Prompt::Columns, columns));
+            }
+            KeyCode::Char('y') => match review.error_count() {
+                0 => return Ok(true),
+                errors => {
+                    screen.status = format!(
+                        "Fix or exclude the {} error(s) before exporting, or press q to cancel",
+                        errors
+                    )
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Applies what was typed at a prompt, returning a status line.
+fn answer(
+    review: &mut Review,
+    screen: &Screen,
+    prompt: Prompt,
+    input: &str,
+    reparse: &mut impl FnMut(&DateColumnRange) -> Result<Vec<Employee>, String>,
+) -> Result<String, String> {
+    let date_range = match prompt {
+        Prompt::Hours => {
+            let hours = match input.trim() {
+                "" => 0.0,
+                hours => hours
+                    .parse::<f32>()
+                    .map_err(|_| format!("`{}` is not a number of hours", hours))?,
+            };
+            let date = review.days()[screen.day];
+            review.set_hours(screen.row, date, hours)?;
+            return Ok(review.edits.last().map(Edit::to_string).unwrap_or_default());
+        }
+        Prompt::Start => {
+            let start = parse_date(input)
+                .ok_or_else(|| format!("`{}` is not a date like 2021-05-03", input))?;
+            moved_dates(
+                &review.date_range,
+                start,
+                review.date_range.head,
+                review.date_range.tail,
+            )
+        }
+        Prompt::Columns => {
+            let (head, tail) = parse_columns(input)
+                .ok_or_else(|| format!("`{}` is not a range of columns like E:K", input))?;
+            let start = review
+                .date_range
+                .start
+                .ok_or_else(|| "set the start date first".to_string())?;
+            moved_dates(&review.date_range, start, head, tail)
+        }
+    };
+
+    let employees = reparse(&date_range)?;
+    let dropped = review.set_dates(date_range, employees);
+    let status = review.edits.last().map(Edit::to_string).unwrap_or_default();
+    Ok([status]
+        .into_iter()
+        .chain(dropped)
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+fn draw<B: Backend>(f: &mut Frame<B>, review: &Review, screen: &mut Screen) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(4),
+                Constraint::Min(6),
+                Constraint::Length(8),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+
+    let days = review.days();
+    screen.row = screen.row.min(review.employees.len().saturating_sub(1));
+    screen.day = screen.day.min(days.len().saturating_sub(1));
+
+    let dates = match review.date_range.range() {
+        Some((start, end)) => format!(
+            "{} to {}",
+            start.format(DATE_FORMAT),
+            end.format(DATE_FORMAT)
+        ),
+        None => "no dates found".to_string(),
+    };
+    let row = review
+        .date_range
+        .row()
+        .map(|row| (row + 1).to_string())
+        .unwrap_or_else(|| "?".to_string());
+    let included = review.included();
+    let header = Paragraph::new(vec![
+        Spans::from(format!(
+            "Dates {} on row {}, columns `{}` to `{}`",
+            dates,
+            row,
+            to_column_letter(review.date_range.head as i32),
+            to_column_letter(review.date_range.tail as i32)
+        )),
+        Spans::from(format!(
+            "{} employees, {} excluded, {:.2} hours, {} edit(s)",
+            included.len(),
+            review.excluded.len(),
+            included
+                .iter()
+                .map(|employee| sum_of_hours(employee.hours.clone()))
+                .sum::<f32>(),
+            review.edits.len()
+        )),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Review `{}`", review.sheet)),
+    );
+    f.render_widget(header, chunks[0]);
+
+    let mut widths = vec![Constraint::Length(10), Constraint::Length(18)];
+    widths.extend(days.iter().map(|_| Constraint::Length(6)));
+    widths.push(Constraint::Length(7));
+    let mut titles = vec!["Employee".to_string(), "Name".to_string()];
+    titles.extend(days.iter().map(|day| day.format("%a %d").to_string()));
+    titles.push("Total".to_string());
+    let grid_header = Row::new(titles).style(Style::default().add_modifier(Modifier::BOLD));
+
+    let rows = review.employees.iter().enumerate().map(|(n, employee)| {
+        let has_error = review
+            .issues
+            .iter()
+            .any(|issue| issue.is_error() && issue.employee.as_deref() == Some(&employee.id));
+        let name = match (
+            employee.last_name.is_empty(),
+            employee.first_name.is_empty(),
+        ) {
+            (true, true) => String::new(),
+            (false, true) => employee.last_name.clone(),
+            _ => format!("{}, {}", employee.last_name, employee.first_name),
+        };
+        let id_style = if has_error {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+
+        let mut cells = vec![
+            Cell::from(employee.id.clone()).style(id_style),
+            Cell::from(name),
+        ];
+        for (day, date) in days.iter().enumerate() {
+            let hours = review.hours_on(n, *date);
+            let text = if hours > 0.0 {
+                format!("{:.2}", hours)
+            } else {
+                "·".to_string()
+            };
+            let style = if n == screen.row && day == screen.day {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            cells.push(Cell::from(text).style(style));
+        }
+        cells.push(Cell::from(format!(
+            "{:.2}",
+            sum_of_hours(employee.hours.clone())
+        )));
+
+        let row = Row::new(cells);
+        if review.excluded.contains(&employee.id) {
+            row.style(
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::CROSSED_OUT),
+            )
+        } else {
+            row
+        }
+    });
+    let grid = Table::new(rows)
+        .header(grid_header)
+        .block(Block::default().borders(Borders::ALL).title("Hours"))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .widths(&widths);
+    screen.table.select(Some(screen.row));
+    f.render_stateful_widget(grid, chunks[1], &mut screen.table);
+
+    let items: Vec<ListItem> = review
+        .issues
+        .iter()
+        .map(|issue| {
+            let (label, color) = match issue.severity {
+                Severity::Error => ("error  ", Color::Red),
+                Severity::Warning => ("warning", Color::Yellow),
+            };
+            ListItem::new(Spans::from(vec![
+                Span::styled(label, Style::default().fg(color)),
+                Span::raw(format!(" {}", issue)),
+            ]))
+        })
+        .collect();
+    let errors = review.error_count();
+    let issues = List::new(items).block(Block::default().borders(Borders::ALL).title(format!(
+        "Validation: {} error(s), {} warning(s)",
+        errors,
+        review.issues.len() - errors
+    )));
+    f.render_widget(issues, chunks[2]);
+
+    let footer = match &screen.prompt {
+        Some((prompt, input)) => {
+            let question = match prompt {
+                Prompt::Hours => match review.employees.get(screen.row) {
+                    Some(employee) => format!(
+                        "Hours for `{}` on {}",
+                        employee.id,
+                        days[screen.day].format(DATE_FORMAT)
+                    ),
+                    None => "Hours".to_string(),
+                },
+                Prompt::Start => "Start date (YYYY-MM-DD)".to_string(),
+                Prompt::Columns => "Date columns (e.g. E:K)".to_string(),
+            };
+            format!("{}: {}_   Enter to apply, Esc to cancel", question, input)
+        }
+        None if !screen.status.is_empty() => screen.status.clone(),
+        None => "Arrows move · type hours or Enter to edit · x exclude · d start date · c date columns · y export · q cancel"
+            .to_string(),
+    };
+    f.render_widget(
+        Paragraph::new(footer).block(Block::default().borders(Borders::ALL)),
+        chunks[3],
+    );
+}
+
+#[test]
+fn it_records_review_edits() {
+    use crate::excel::records::{employee_with, shift_on, test_start};
+
+    let start = test_start();
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(6));
+
+    let employee = |id: &str, days: &[i64]| {
+        let shifts = days.iter().map(|day| {
+            let mut shift = shift_on(*day, 8);
+            shift.job = "A-100".to_string();
+            shift
+        });
+        employee_with(id, shifts.collect())
+    };
+    let mut review = Review::new(
+        "Week 1",
+        date_range.clone(),
+        vec![employee("1001", &[0, 1]), employee("1002", &[0, 0])],
+    );
+    review.employees[0].hours[0].earning = "VAC".to_string();
+
+    review.set_hours(0, start, 7.5).unwrap();
+    review.set_hours(0, start + Duration::days(2), 4.0).unwrap();
+    review.set_hours(0, start + Duration::days(1), 0.0).unwrap();
+    review.set_hours(0, start, 7.5).unwrap();
+    assert!(review.set_hours(1, start, 4.0).is_err());
+    assert!(review.set_hours(0, start + Duration::days(9), 4.0).is_err());
+    review.toggle_excluded(1);
+
+    assert_eq!(sum_of_hours(review.employees[0].hours.clone()), 11.5);
+    assert_eq!(review.employees[0].hours[1].job, "A-100");
+    assert_eq!(review.employees[0].hours[1].earning, "HRLY");
+    assert_eq!(review.employees[0].hours[1].col, 6);
+    let ids: Vec<String> = review.included().into_iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec!["1001"]);
+
+    // Moving the dates a day later keeps the changes made on the days still in the range.
+    let later = moved_dates(&date_range, start + Duration::days(1), 5, 11);
+    // The change on the day that left the range is dropped.
+    let dropped = review.set_dates(
+        later,
+        vec![employee("1001", &[0, 1]), employee("1002", &[])],
+    );
+    assert_eq!(
+        dropped,
+        vec!["dropped \"changed employee `1001` on 2021-05-03 from 8.00 to 7.50 hours\": May 03, 2021 is not one of the timecard's dates"]
+    );
+    assert_eq!(review.hours_on(0, start + Duration::days(2)), 4.0);
+    assert_eq!(review.hours_on(0, start + Duration::days(1)), 0.0);
+
+    let edits: Vec<String> = review.edits.iter().map(Edit::to_string).collect();
+    assert_eq!(
+        edits,
+        vec![
+            "changed employee `1001` on 2021-05-05 from 0.00 to 4.00 hours",
+            "changed employee `1001` on 2021-05-04 from 8.00 to 0.00 hours",
+            "excluded employee `1002`",
+            "dates start 2021-05-04 in columns `F` to `L`",
+        ]
+    );
+    assert_eq!(parse_columns("e:k"), Some((4, 10)));
+    assert_eq!(parse_date("May 03, 2021"), Some(start));
+}
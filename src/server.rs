@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use thiserror::Error;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+use crate::excel::sage::{write_csv, TIMECARD_DETAIL_HEADERS, TIMECARD_HEADER_HEADERS};
+use crate::excel::sage_import::read_sage_import;
+use crate::export::IssueEntry;
+use crate::validation::Issue;
+
+const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("could not listen on `{0}`: {1}")]
+    Bind(String, String),
+    #[error("io error")]
+    Io(std::io::Error),
+}
+
+/// A timecard workbook uploaded to be converted.
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub workbook: PathBuf,
+    /// Where the Sage import workbook is to be written.
+    pub output: PathBuf,
+    /// The query string's parameters: the `sheet` and any option overrides.
+    pub params: HashMap<String, String>,
+}
+
+/// What happened to an upload.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// The Sage import was written, with the warnings found along the way.
+    Exported(Vec<Issue>),
+    /// The timecard has validation errors, so nothing was written.
+    Invalid(Vec<Issue>),
+    /// The upload or its parameters couldn't be read, which the client has to fix.
+    Rejected(String),
+    /// Converting failed for some other reason, e.g. the output couldn't be written.
+    Failed(String),
+}
+
+#[derive(Debug, Serialize)]
+struct ConvertResponse {
+    id: usize,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    issues: Vec<IssueEntry>,
+    /// Where to download the Sage import workbook from, when one was written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download: Option<String>,
+    /// Where to download the import's header and detail sheets as CSV files instead.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    csv: Vec<String>,
+}
+
+impl ConvertResponse {
+    fn failed(id: usize, message: String) -> Self {
+        Self {
+            id,
+            ok: false,
+            error: Some(message),
+            issues: Vec::new(),
+            download: None,
+            csv: Vec::new(),
+        }
+    }
+}
+
+/// A small HTTP API over the converter, meant to listen on localhost for an internal
+/// portal.
+///
+/// - `POST /timecards?sheet=Week%201` with the workbook as the body converts it, taking
+///   option overrides (e.g. `timecard-id`) as further parameters and `filename` to give
+///   the workbook's type, and answers with the validation results as JSON.
+/// - `GET /imports/<id>.xlsx` downloads the Sage import workbook of a conversion, and
+///   `GET /imports/<id>-header.csv` and `GET /imports/<id>-detail.csv` its two sheets as
+///   CSV.
+///
+/// Uploads and Sage imports are deleted once they are older than `keep`, checked whenever
+/// another workbook is uploaded.
+pub struct Api {
+    server: Server,
+    dir: PathBuf,
+    keep: Duration,
+    next_id: usize,
+}
+
+impl Api {
+    /// Listens on `address`, keeping uploads and generated workbooks in `dir` for `keep`.
+    pub fn bind(address: &str, dir: PathBuf, keep: Duration) -> Result<Self, ServerError> {
+        fs::create_dir_all(&dir).map_err(ServerError::Io)?;
+        let server = Server::http(address)
+            .map_err(|err| ServerError::Bind(address.to_string(), err.to_string()))?;
+
+        Ok(Self {
+            server,
+            dir,
+            keep,
+            next_id: 1,
+        })
+    }
+
+    pub fn address(&self) -> Option<std::net::SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Deletes the uploads and Sage imports older than `keep`, returning how many there were.
+    pub fn clean(&self) -> io::Result<usize> {
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let age = entry
+                .metadata()?
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            if age.map(|age| age >= self.keep).unwrap_or(false) {
+                fs::remove_file(entry.path())?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Answers requests one at a time, converting uploads with `convert`.
+    pub fn run(&mut self, mut convert: impl FnMut(&Upload) -> Outcome) -> Result<(), ServerError> {
+        loop {
+            let request = self.server.recv().map_err(ServerError::Io)?;
+            let method = request.method().clone();
+            let url = request.url().to_string();
+            if let Err(err) = self.handle(request, &mut convert) {
+                error!("Failed to answer {} {}: {}", method, url, err);
+            }
+        }
+    }
+
+    fn handle(
+        &mut self,
+        request: Request,
+        convert: &mut impl FnMut(&Upload) -> Outcome,
+    ) -> io::Result<()> {
+        let (path, params) = parse_url(request.url());
+        info!("{} {}", request.method(), path);
+
+        match (request.method(), path.as_str()) {
+            (Method::Post, "/timecards") => self.convert(request, params, convert),
+            (Method::Get, path) if path.starts_with("/imports/") => {
+                let name = &path["/imports/".len()..];
+                self.download(request, name)
+            }
+            _ => request.respond(not_found()),
+        }
+    }
+
+    fn convert(
+        &mut self,
+        mut request: Request,
+        params: HashMap<String, String>,
+        convert: &mut impl FnMut(&Upload) -> Outcome,
+    ) -> io::Result<()> {
+        match self.clean() {
+            Ok(0) => {}
+            Ok(removed) => info!("Removed {} old upload and import file(s)", removed),
+            Err(err) => warn!("Failed to remove old uploads from {:?}: {}", self.dir, err),
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let extension = params
+            .get("filename")
+            .and_then(|name| Path::new(name).extension())
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "xlsx".to_string());
+        let upload = Upload {
+            workbook: self.dir.join(format!("{}-upload.{}", id, extension)),
+            output: self.dir.join(format!("{}.xlsx", id)),
+            params,
+        };
+        let mut file = File::create(&upload.workbook)?;
+        io::copy(request.as_reader(), &mut file)?;
+        drop(file);
+
+        let (status, response) = match convert(&upload) {
+            Outcome::Exported(issues) => (
+                200,
+                ConvertResponse {
+                    id,
+                    ok: true,
+                    error: None,
+                    issues: issues.iter().map(IssueEntry::from).collect(),
+                    download: Some(format!("/imports/{}.xlsx", id)),
+                    csv: vec![
+                        format!("/imports/{}-header.csv", id),
+                        format!("/imports/{}-detail.csv", id),
+                    ],
+                },
+            ),
+            Outcome::Invalid(issues) => (
+                422,
+                ConvertResponse {
+                    id,
+                    ok: false,
+                    error: Some("the timecard has validation errors".to_string()),
+                    issues: issues.iter().map(IssueEntry::from).collect(),
+                    download: None,
+                    csv: Vec::new(),
+                },
+            ),
+            Outcome::Rejected(message) => (400, ConvertResponse::failed(id, message)),
+            Outcome::Failed(message) => (500, ConvertResponse::failed(id, message)),
+        };
+
+        request.respond(json_response(status, &response))
+    }
+
+    fn download(&self, request: Request, name: &str) -> io::Result<()> {
+        // Only ids are accepted, so nothing outside the folder can be asked for.
+        let (id, kind) = match name.split_once('.') {
+            Some((id, "xlsx")) => (id, None),
+            Some((id, "csv")) => match id.rsplit_once('-') {
+                Some((id, "header")) => (id, Some("header")),
+                Some((id, "detail")) => (id, Some("detail")),
+                _ => return request.respond(not_found()),
+            },
+            _ => return request.respond(not_found()),
+        };
+        let path = id
+            .parse::<usize>()
+            .ok()
+            .map(|id| self.dir.join(format!("{}.xlsx", id)))
+            .filter(|path| path.is_file());
+        let path = match path {
+            Some(path) => path,
+            None => return request.respond(not_found()),
+        };
+
+        let kind = match kind {
+            Some(kind) => kind,
+            None => {
+                let content_type =
+                    Header::from_bytes("Content-Type", XLSX_CONTENT_TYPE).expect("valid header");
+                return request
+                    .respond(Response::from_file(File::open(path)?).with_header(content_type));
+            }
+        };
+
+        let mut csv = Vec::new();
+        let written = read_sage_import(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|import| {
+                let (headers, rows) = match kind {
+                    "header" => (TIMECARD_HEADER_HEADERS, &import.header),
+                    _ => (TIMECARD_DETAIL_HEADERS, &import.detail),
+                };
+                write_csv(headers, rows, &mut csv).map_err(|err| err.to_string())
+            });
+        match written {
+            Ok(()) => {
+                let content_type =
+                    Header::from_bytes("Content-Type", "text/csv").expect("valid header");
+                request.respond(Response::from_data(csv).with_header(content_type))
+            }
+            Err(message) => request.respond(json_response(
+                500,
+                &serde_json::json!({ "error": format!("could not read the Sage import: {}", message) }),
+            )),
+        }
+    }
+}
+
+fn not_found() -> Response<io::Cursor<Vec<u8>>> {
+    json_response(404, &serde_json::json!({"error": "not found"}))
+}
+
+fn json_response(status: u16, body: &impl Serialize) -> Response<io::Cursor<Vec<u8>>> {
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    Response::from_string(serde_json::to_string_pretty(body).unwrap_or_default())
+        .with_status_code(StatusCode(status))
+        .with_header(content_type)
+}
+
+/// Decodes `%XX` escapes and `+` for spaces in a query string part.
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Splits a request url into its path and query parameters.
+fn parse_url(url: &str) -> (String, HashMap<String, String>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(name), decode(value))
+        })
+        .collect();
+
+    (decode(path), params)
+}
+
+#[test]
+fn it_converts_uploads_over_http() {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    let dir = std::env::temp_dir().join(format!("autotimecard-api-{}", std::process::id()));
+    let mut api = Api::bind("127.0.0.1:0", dir.clone(), Duration::from_secs(3600)).unwrap();
+    let address = api.address().unwrap();
+    std::thread::spawn(move || {
+        api.run(|upload| {
+            match upload.params.get("sheet").map(String::as_str) {
+                Some("Week 1") => {}
+                Some("Missing") => return Outcome::Rejected("no such sheet".to_string()),
+                Some("Locked") => return Outcome::Failed("output is locked".to_string()),
+                _ => return Outcome::Invalid(vec![Issue::error(None, "no sheet".to_string())]),
+            }
+            let uploaded = fs::read(&upload.workbook).unwrap();
+            fs::write(&upload.output, [b"sage:".as_slice(), &uploaded].concat()).unwrap();
+            Outcome::Exported(vec![Issue::warning(Some("1001"), "no rate".to_string())])
+        })
+    });
+
+    let send = |request: String| {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let post = |query: &str| {
+        send(format!(
+            "POST /timecards?{} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 4\r\n\r\nxlsx",
+            query
+        ))
+    };
+
+    let converted = post("sheet=Week+1&filename=Week%201.xlsx");
+    assert!(converted.starts_with("HTTP/1.1 200"));
+    assert!(converted.contains(r#""download": "/imports/1.xlsx""#));
+    assert!(converted.contains(r#""employee": "1001""#));
+    assert!(dir.join("1-upload.xlsx").is_file());
+
+    assert!(converted.contains(r#""/imports/1-detail.csv""#));
+    assert!(post("sheet=Week%202").starts_with("HTTP/1.1 422"));
+    assert!(post("sheet=Missing").starts_with("HTTP/1.1 400"));
+    assert!(post("sheet=Locked").starts_with("HTTP/1.1 500"));
+
+    let download = send(
+        "GET /imports/1.xlsx HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string(),
+    );
+    assert!(download.starts_with("HTTP/1.1 200"));
+    assert!(download.ends_with("sage:xlsx"));
+    let missing =
+        send("GET /imports/../1-upload.xlsx HTTP/1.1\r\nConnection: close\r\n\r\n".to_string());
+    assert!(missing.starts_with("HTTP/1.1 404"));
+    // The test's import isn't a real workbook, so it can't be read back for CSV.
+    let csv = send("GET /imports/1-header.csv HTTP/1.1\r\nConnection: close\r\n\r\n".to_string());
+    assert!(csv.starts_with("HTTP/1.1 500"));
+    let csv = send("GET /imports/1-summary.csv HTTP/1.1\r\nConnection: close\r\n\r\n".to_string());
+    assert!(csv.starts_with("HTTP/1.1 404"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn it_removes_old_uploads() {
+    let dir = std::env::temp_dir().join(format!("autotimecard-clean-{}", std::process::id()));
+    let api = Api::bind("127.0.0.1:0", dir.clone(), Duration::ZERO).unwrap();
+    fs::write(dir.join("1-upload.xlsx"), b"xlsx").unwrap();
+    fs::write(dir.join("1.xlsx"), b"sage").unwrap();
+
+    assert_eq!(api.clean().unwrap(), 2);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
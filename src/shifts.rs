@@ -0,0 +1,291 @@
+use std::path::Path;
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::employees::{Employee, Shift};
+use crate::excel::records::{read_records, Record};
+use crate::excel::ExcelError;
+use crate::validation::Issue;
+
+const TIME_FORMAT: &str = "%H:%M";
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// When a Sage shift number applies: a code typed after the hours, a time-of-day window,
+/// a set of weekdays, or a window on certain weekdays.
+#[derive(Debug, Clone, Default)]
+pub struct ShiftRule {
+    pub number: String,
+    pub code: String,
+    pub days: Vec<Weekday>,
+    /// Start and stop of the window, which runs past midnight when stop is before start.
+    pub window: Option<(NaiveTime, NaiveTime)>,
+}
+
+impl ShiftRule {
+    fn from_record(record: &Record) -> Result<Option<Self>, ExcelError> {
+        let number = match record.get("SHIFTNUM") {
+            Some(number) => number.to_string(),
+            None => return Ok(None),
+        };
+
+        let window = match (record.get("START"), record.get("STOP")) {
+            (Some(start), Some(stop)) => Some((parse_time(start)?, parse_time(stop)?)),
+            (None, None) => None,
+            _ => {
+                return Err(ExcelError::Msg(format!(
+                    "shift {} needs both a `START` and `STOP` time",
+                    number
+                )))
+            }
+        };
+
+        Ok(Some(Self {
+            code: record.get_string("CODE"),
+            days: parse_days(record.get("DAYS").unwrap_or_default())?,
+            window,
+            number,
+        }))
+    }
+
+    /// Whether a minute worked at `at` falls under this rule. Rules with neither days nor a
+    /// window only ever apply by their code.
+    fn matches(&self, at: NaiveDateTime) -> bool {
+        if !self.days.is_empty() && !self.days.contains(&at.weekday()) {
+            return false;
+        }
+
+        match self.window {
+            Some((start, stop)) if start <= stop => at.time() >= start && at.time() < stop,
+            Some((start, stop)) => at.time() >= start || at.time() < stop,
+            None => !self.days.is_empty(),
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, ExcelError> {
+    NaiveTime::parse_from_str(value, TIME_FORMAT).map_err(|_| {
+        ExcelError::Msg(format!(
+            "`{}` is not a valid time, expected the format HH:MM",
+            value
+        ))
+    })
+}
+
+/// Parses weekdays written as a list and/or ranges, e.g. `Sat,Sun` or `Mon-Fri`.
+fn parse_days(value: &str) -> Result<Vec<Weekday>, ExcelError> {
+    let parse_day = |day: &str| {
+        day.trim()
+            .parse::<Weekday>()
+            .map_err(|_| ExcelError::Msg(format!("`{}` is not a day of the week", day.trim())))
+    };
+
+    let mut days = Vec::new();
+    for part in value.split(',').filter(|part| !part.trim().is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let first = parse_day(first)?.num_days_from_monday() as usize;
+                let last = parse_day(last)?.num_days_from_monday() as usize;
+                let mut n = first;
+                loop {
+                    days.push(WEEKDAYS[n]);
+                    if n == last {
+                        break;
+                    }
+                    n = (n + 1) % WEEKDAYS.len();
+                }
+            }
+            None => days.push(parse_day(part)?),
+        }
+    }
+
+    Ok(days)
+}
+
+/// Shift differential rules keyed by Sage shift number, with `SHIFTNUM`, `CODE`, `DAYS`,
+/// `START` and `STOP` columns. The first rule that matches wins.
+#[derive(Debug, Clone, Default)]
+pub struct ShiftRules {
+    rules: Vec<ShiftRule>,
+}
+
+impl ShiftRules {
+    pub fn load(path: &Path) -> Result<Self, ExcelError> {
+        Self::from_records(read_records(path)?)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> Result<Self, ExcelError> {
+        let mut rules = Vec::new();
+        for record in records.iter() {
+            if let Some(rule) = ShiftRule::from_record(record)? {
+                rules.push(rule);
+            }
+        }
+
+        Ok(Self { rules })
+    }
+
+    fn number_at(&self, at: NaiveDateTime) -> String {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(at))
+            .map(|rule| rule.number.clone())
+            .unwrap_or_default()
+    }
+
+    /// Assigns shift numbers to a day's hours, splitting shifts with clock times into one
+    /// piece per stretch of time that falls under the same shift number.
+    pub fn classify(&self, shift: &Shift) -> Result<Vec<Shift>, String> {
+        if !shift.shift_code.is_empty() {
+            let rule = self
+                .rules
+                .iter()
+                .find(|rule| rule.code.eq_ignore_ascii_case(&shift.shift_code))
+                .ok_or_else(|| {
+                    format!(
+                        "shift code `{}` is not in the shift rules",
+                        shift.shift_code
+                    )
+                })?;
+            let mut shift = shift.clone();
+            shift.shift_number = rule.number.clone();
+            shift.shift_code.clear();
+            return Ok(vec![shift]);
+        }
+
+        let start = match shift.start {
+            Some(start) => start,
+            None => {
+                // Without clock times, only whole-day (weekday) rules can apply.
+                let mut shift = shift.clone();
+                shift.shift_number = self
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.window.is_none())
+                    .find(|rule| rule.matches(shift.date.naive_utc().and_hms(0, 0, 0)))
+                    .map(|rule| rule.number.clone())
+                    .unwrap_or_default();
+                return Ok(vec![shift]);
+            }
+        };
+
+        let began = shift.date.naive_utc().and_time(start);
+        let mut pieces: Vec<Shift> = Vec::new();
+        for minute in 0..shift.duration.num_minutes() {
+            let at = began + Duration::minutes(minute);
+            let number = self.number_at(at);
+
+            match pieces.last_mut() {
+                Some(piece) if piece.shift_number == number => {
+                    piece.duration = piece.duration + Duration::minutes(1);
+                }
+                _ => {
+                    let mut piece = shift.clone();
+                    piece.duration = Duration::minutes(1);
+                    piece.start = Some(at.time());
+                    piece.shift_number = number;
+                    pieces.push(piece);
+                }
+            }
+        }
+        for piece in pieces.iter_mut() {
+            piece.stop = piece.start.map(|start| start + piece.duration);
+        }
+
+        Ok(pieces)
+    }
+
+    pub fn apply(&self, employees: &mut [Employee]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for employee in employees.iter_mut() {
+            let mut hours = Vec::new();
+            for shift in employee.hours.iter() {
+                match self.classify(shift) {
+                    Ok(pieces) => hours.extend(pieces),
+                    Err(message) => {
+                        issues.push(Issue::error(Some(&employee.id), message));
+                        hours.push(shift.clone());
+                    }
+                }
+            }
+            employee.hours = hours;
+        }
+
+        issues
+    }
+}
+
+/// Flags hours with a shift code (e.g. the `N` in `8N`) that no shift or accrual rule took,
+/// which would otherwise be exported as regular hours.
+pub fn unknown_codes(employees: &[Employee]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for employee in employees.iter() {
+        for shift in employee.hours.iter() {
+            if !shift.shift_code.is_empty() {
+                issues.push(Issue::error(
+                    Some(&employee.id),
+                    format!(
+                        "shift code `{}` on {} is not in the shift or accrual rules",
+                        shift.shift_code,
+                        shift.date.format("%Y-%m-%d")
+                    ),
+                ));
+            }
+        }
+    }
+    issues
+}
+
+#[test]
+fn it_splits_night_hours() {
+    use crate::excel::records::records_of;
+
+    let rows = [
+        ["SHIFTNUM", "CODE", "DAYS", "START", "STOP"],
+        ["3", "W", "Sat-Sun", "", ""],
+        ["2", "N", "", "22:00", "06:00"],
+    ];
+    let rules = ShiftRules::from_records(records_of(&rows)).unwrap();
+
+    // A Thursday evening shift running into the night
+    let thursday =
+        chrono::Date::<chrono::Utc>::from_utc(chrono::NaiveDate::from_ymd(2021, 5, 6), chrono::Utc);
+    let mut shift = Shift::new(4, Duration::hours(10), thursday);
+    shift.start = NaiveTime::from_hms_opt(18, 0, 0);
+
+    let pieces = rules.classify(&shift).unwrap();
+    let summary: Vec<(String, f32)> = pieces
+        .iter()
+        .map(|piece| (piece.shift_number.clone(), piece.sum_of_shift()))
+        .collect();
+    assert_eq!(summary, vec![(String::new(), 4.0), ("2".to_string(), 6.0)]);
+    assert_eq!(pieces[1].stop, NaiveTime::from_hms_opt(4, 0, 0));
+
+    let saturday = Shift::new(4, Duration::hours(8), thursday + Duration::days(2));
+    assert_eq!(rules.classify(&saturday).unwrap()[0].shift_number, "3");
+
+    let mut coded = Shift::new(4, Duration::hours(8), thursday);
+    coded.shift_code = "x".to_string();
+    assert!(rules.classify(&coded).is_err());
+
+    // Codes a rule took are cleared, and any left over are reported.
+    let mut night = coded.clone();
+    night.shift_code = "n".to_string();
+    let mut employee = Employee::new("1001".to_string());
+    employee.hours = rules.classify(&night).unwrap();
+    assert_eq!(employee.hours[0].shift_number, "2");
+    assert!(unknown_codes(&[employee.clone()]).is_empty());
+    employee.hours.push(coded);
+    assert_eq!(
+        unknown_codes(&[employee])[0].message,
+        "shift code `x` on 2021-05-06 is not in the shift or accrual rules"
+    );
+}
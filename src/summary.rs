@@ -0,0 +1,183 @@
+use chrono::{Date, Utc};
+
+use crate::employees::Employee;
+use crate::excel::timecards::DateColumnRange;
+
+/// Per employee hours for the pay period laid out as a grid of days, for people to review
+/// before the timecard is imported.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub days: Vec<Date<Utc>>,
+    pub rows: Vec<SummaryRow>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryRow {
+    pub id: String,
+    pub name: String,
+    pub dist_code: String,
+    pub exp_account: String,
+    pub ot_account: String,
+    /// Hours for each day of the period, in the same order as [`Summary::days`].
+    pub daily: Vec<f32>,
+    pub regular: f32,
+    pub overtime: f32,
+}
+
+impl SummaryRow {
+    pub fn total(&self) -> f32 {
+        self.regular + self.overtime
+    }
+}
+
+impl Summary {
+    /// Builds the summary of every employee with hours.
+    ///
+    /// Overtime is estimated as the regular hours worked over `weekly_overtime` in each week
+    /// of the period, counting weeks from its first day, as in the labour cost estimate.
+    /// Sage still works out the overtime it pays from the employee's OT schedule on import.
+    pub fn new(employees: &[Employee], date_range: &DateColumnRange, weekly_overtime: f32) -> Self {
+        let days: Vec<Date<Utc>> = date_range.clone().collect();
+
+        let rows = employees
+            .iter()
+            .filter_map(|employee| {
+                let mut daily = vec![0.0; days.len()];
+                let mut worked = vec![0.0; days.len()];
+                for shift in employee.hours.iter() {
+                    if let Some(n) = days.iter().position(|day| *day == shift.date) {
+                        daily[n] += shift.sum_of_shift();
+                        if shift.counts_toward_overtime() {
+                            worked[n] += shift.sum_of_shift();
+                        }
+                    }
+                }
+                if daily.iter().all(|hours| *hours <= 0.0) {
+                    return None;
+                }
+
+                let (mut regular, mut overtime) = (0.0, 0.0);
+                for (week, worked) in daily.chunks(7).zip(worked.chunks(7)) {
+                    let hours: f32 = week.iter().sum();
+                    let over = (worked.iter().sum::<f32>() - weekly_overtime).max(0.0);
+                    regular += hours - over;
+                    overtime += over;
+                }
+
+                let name = match (
+                    employee.last_name.is_empty(),
+                    employee.first_name.is_empty(),
+                ) {
+                    (false, false) => format!("{}, {}", employee.last_name, employee.first_name),
+                    _ => format!("{}{}", employee.last_name, employee.first_name),
+                };
+
+                Some(SummaryRow {
+                    id: employee.id.clone(),
+                    name,
+                    dist_code: employee.dist_code.clone(),
+                    exp_account: employee.exp_account.clone(),
+                    ot_account: employee.overtime_account().to_string(),
+                    daily,
+                    regular,
+                    overtime,
+                })
+            })
+            .collect();
+
+        Self { days, rows }
+    }
+
+    pub fn daily_totals(&self) -> Vec<f32> {
+        (0..self.days.len())
+            .map(|n| self.rows.iter().map(|row| row.daily[n]).sum())
+            .collect()
+    }
+
+    pub fn regular(&self) -> f32 {
+        self.rows.iter().map(|row| row.regular).sum()
+    }
+
+    pub fn overtime(&self) -> f32 {
+        self.rows.iter().map(|row| row.overtime).sum()
+    }
+
+    pub fn total(&self) -> f32 {
+        self.rows.iter().map(|row| row.total()).sum()
+    }
+}
+
+#[test]
+fn it_splits_weekly_overtime() {
+    use crate::employees::Shift;
+    use chrono::{Duration, TimeZone};
+
+    let start = Utc.ymd(2021, 5, 3);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 17;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(13));
+
+    let mut employee = Employee::new("1001".to_string());
+    employee.last_name = "Doe".to_string();
+    employee.first_name = "Jane".to_string();
+    // 45 hours the first week, 30 the second
+    for day in 0..5 {
+        let date = start + Duration::days(day);
+        employee.hours.push(Shift::new(4, Duration::hours(9), date));
+        let date = start + Duration::days(day + 7);
+        employee.hours.push(Shift::new(4, Duration::hours(6), date));
+    }
+
+    let summary = Summary::new(
+        &[employee, Employee::new("1002".to_string())],
+        &date_range,
+        40.0,
+    );
+    assert_eq!(summary.rows.len(), 1);
+    assert_eq!(summary.rows[0].name, "Doe, Jane");
+    assert_eq!(summary.rows[0].daily[0], 9.0);
+    assert_eq!(summary.rows[0].regular, 70.0);
+    assert_eq!(summary.rows[0].overtime, 5.0);
+    assert_eq!(summary.daily_totals()[7], 6.0);
+    assert_eq!(summary.total(), 75.0);
+}
+
+#[test]
+fn it_only_counts_hours_worked_toward_overtime() {
+    use crate::accruals::{Accrual, Bank, PayOrAccrue};
+    use crate::employees::Shift;
+    use chrono::{Duration, TimeZone};
+
+    let start = Utc.ymd(2021, 5, 3);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(6));
+
+    let mut employee = Employee::new("1001".to_string());
+    // 38 hours worked, 8 hours of vacation and 4 hours banked as comp time
+    for day in 0..4 {
+        let shift = Shift::new(4, Duration::hours(9), start + Duration::days(day));
+        employee.hours.push(shift);
+    }
+    employee
+        .hours
+        .push(Shift::new(8, Duration::hours(2), start + Duration::days(4)));
+    let mut vacation = Shift::new(8, Duration::hours(8), start + Duration::days(4));
+    vacation.earning = "VAC".to_string();
+    employee.hours.push(vacation);
+    let mut banked = Shift::new(9, Duration::hours(4), start + Duration::days(5));
+    banked.accrual = Some(Accrual {
+        bank: Bank::Comp,
+        action: PayOrAccrue::Accrue,
+    });
+    employee.hours.push(banked);
+
+    let summary = Summary::new(&[employee], &date_range, 40.0);
+    assert_eq!(summary.rows[0].daily[4], 10.0);
+    assert_eq!(summary.rows[0].regular, 50.0);
+    assert_eq!(summary.rows[0].overtime, 0.0);
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Date, Utc};
+
+use crate::employees::{sum_of_hours, Employee};
+use crate::excel::timecards::DateColumnRange;
+use crate::validation::Issue;
+
+/// Longest timecard id Sage accepts.
+pub const MAX_ID_LEN: usize = 6;
+/// Longest timecard description Sage accepts.
+pub const MAX_DESC_LEN: usize = 60;
+const DEFAULT_DATE_FORMAT: &str = "%y%m%d";
+
+/// Templates for the `TIMECARD` id and `TCARDDESC` description written for each employee.
+///
+/// Templates can use `{sheet}`, `{start}` and `{end}` (the period's first and last day,
+/// optionally with a date format such as `{end:%m%d}`) and `{dist}` (the dist code).
+/// `{sheet}` and `{dist}` can be cut to their first characters, e.g. `{sheet:.4}`. Ids
+/// are uppercased.
+#[derive(Debug, Clone)]
+pub struct TimecardTemplate {
+    pub id: String,
+    pub desc: String,
+}
+
+/// Formats a date with a format from a template, which chrono would panic on if invalid.
+fn format_date(date: Date<Utc>, format: &str) -> Result<String, String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("invalid date format `{}` in template", format));
+    }
+
+    Ok(date.format(format).to_string())
+}
+
+/// Cuts text to the length in a `.N` format from a template.
+fn truncate(value: &str, format: Option<&str>) -> Result<String, String> {
+    let format = match format {
+        Some(format) => format,
+        None => return Ok(value.to_string()),
+    };
+    let len = format
+        .strip_prefix('.')
+        .and_then(|len| len.parse::<usize>().ok())
+        .ok_or_else(|| {
+            format!(
+                "invalid format `{}` in template, only a length like `.4` can be given",
+                format
+            )
+        })?;
+
+    Ok(value.chars().take(len).collect())
+}
+
+fn render(
+    template: &str,
+    sheet: &str,
+    (start, end): (Date<Utc>, Date<Utc>),
+    employee: &Employee,
+) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        rendered.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed `{{` in template `{}`", template))?
+            + open;
+
+        let placeholder = &rest[open + 1..close];
+        let (name, format) = match placeholder.split_once(':') {
+            Some((name, format)) => (name, Some(format)),
+            None => (placeholder, None),
+        };
+        let date_format = format.unwrap_or(DEFAULT_DATE_FORMAT);
+        match name {
+            "sheet" => rendered.push_str(&truncate(sheet, format)?),
+            "start" => rendered.push_str(&format_date(start, date_format)?),
+            "end" => rendered.push_str(&format_date(end, date_format)?),
+            "dist" => rendered.push_str(&truncate(&employee.dist_code, format)?),
+            _ => {
+                return Err(format!(
+                    "unknown placeholder `{{{}}}` in template `{}`",
+                    placeholder, template
+                ))
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+impl TimecardTemplate {
+    /// Sets every employee's timecard id and description, reporting ids and descriptions
+    /// Sage would reject.
+    pub fn apply(
+        &self,
+        sheet: &str,
+        date_range: &DateColumnRange,
+        employees: &mut [Employee],
+    ) -> Vec<Issue> {
+        let range = match date_range.range() {
+            Some(range) => range,
+            None => return vec![Issue::error(None, "timecard has no date range".to_string())],
+        };
+
+        let mut issues = Vec::new();
+        let mut problems: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for employee in employees.iter_mut() {
+            if sum_of_hours(employee.hours.clone()) <= 0.0 {
+                continue;
+            }
+
+            let rendered = render(&self.id, sheet, range, employee).and_then(|id| {
+                let desc = render(&self.desc, sheet, range, employee)?;
+                Ok((id.trim().to_uppercase(), desc.trim().to_string()))
+            });
+            let (id, desc) = match rendered {
+                Ok(rendered) => rendered,
+                Err(message) => {
+                    issues.push(Issue::error(None, message));
+                    break;
+                }
+            };
+
+            if id.is_empty()
+                || id.chars().count() > MAX_ID_LEN
+                || !id.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                problems
+                    .entry(format!(
+                        "timecard id `{}` must be 1 to {} letters or digits",
+                        id, MAX_ID_LEN
+                    ))
+                    .or_default()
+                    .push(employee.id.clone());
+            }
+            if desc.chars().count() > MAX_DESC_LEN {
+                problems
+                    .entry(format!(
+                        "timecard description `{}` is longer than {} characters",
+                        desc, MAX_DESC_LEN
+                    ))
+                    .or_default()
+                    .push(employee.id.clone());
+            }
+
+            employee.timecard = id;
+            employee.timecard_desc = desc;
+        }
+
+        issues.extend(problems.into_iter().map(|(problem, ids)| {
+            Issue::error(
+                None,
+                format!(
+                    "{}, use --timecard-id to set another (employees {})",
+                    problem,
+                    ids.join(", ")
+                ),
+            )
+        }));
+
+        issues
+    }
+}
+
+/// Logs each timecard id that will be imported and how many employees are on it.
+pub fn report_ids(employees: &[Employee]) {
+    let mut ids: BTreeMap<&str, usize> = BTreeMap::new();
+    for employee in employees.iter() {
+        if sum_of_hours(employee.hours.clone()) > 0.0 {
+            *ids.entry(employee.timecard.as_str()).or_default() += 1;
+        }
+    }
+
+    for (id, count) in ids {
+        info!("Timecard `{}`: {} employee(s)", id, count);
+    }
+}
+
+#[test]
+fn it_renders_timecard_ids() {
+    use crate::employees::Shift;
+    use chrono::{Duration, NaiveDate};
+
+    let start = Date::<Utc>::from_utc(NaiveDate::from_ymd(2021, 5, 2), Utc);
+    let mut date_range = DateColumnRange::new();
+    date_range.head = 4;
+    date_range.tail = 10;
+    date_range.start = Some(start);
+    date_range.end = Some(start + Duration::days(6));
+
+    let mut employee = Employee::new("1001".to_string());
+    employee.dist_code = "sh".to_string();
+    employee
+        .hours
+        .push(Shift::new(4, Duration::hours(8), start));
+    let mut employees = vec![employee];
+
+    let template = TimecardTemplate {
+        id: "{dist}{end:%m%d}".to_string(),
+        desc: "Week of {start:%B %d} ({sheet})".to_string(),
+    };
+    let issues = template.apply("Week 1", &date_range, &mut employees);
+    assert!(issues.is_empty());
+    assert_eq!(employees[0].timecard, "SH0508");
+    assert_eq!(employees[0].timecard_desc, "Week of May 02 (Week 1)");
+
+    let sheet_name = TimecardTemplate {
+        id: "{sheet}".to_string(),
+        desc: String::new(),
+    };
+    let issues = sheet_name.apply("Week of May 3", &date_range, &mut employees);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("(employees 1001)"));
+
+    let unknown = TimecardTemplate {
+        id: "{week}".to_string(),
+        desc: String::new(),
+    };
+    assert_eq!(unknown.apply("W1", &date_range, &mut employees).len(), 1);
+
+    let bad_format = TimecardTemplate {
+        id: "{end:%Q}".to_string(),
+        desc: String::new(),
+    };
+    let issues = bad_format.apply("W1", &date_range, &mut employees);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].message, "invalid date format `%Q` in template");
+
+    let truncated = TimecardTemplate {
+        id: "{sheet:.2}{dist:.1}".to_string(),
+        desc: "{sheet:.20}".to_string(),
+    };
+    assert!(truncated
+        .apply("Week 1", &date_range, &mut employees)
+        .is_empty());
+    assert_eq!(employees[0].timecard, "WES");
+    assert_eq!(employees[0].timecard_desc, "Week 1");
+
+    let bad_length = TimecardTemplate {
+        id: "{dist:%m}".to_string(),
+        desc: String::new(),
+    };
+    let issues = bad_length.apply("W1", &date_range, &mut employees);
+    assert_eq!(
+        issues[0].message,
+        "invalid format `%m` in template, only a length like `.4` can be given"
+    );
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found while checking a timecard, optionally tied to an employee.
+///
+/// Warnings are reported and the export continues; errors stop the run before any
+/// Sage import is written.
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub employee: Option<String>,
+    pub message: String,
+}
+
+impl Issue {
+    pub fn new(severity: Severity, employee: Option<&str>, message: String) -> Self {
+        Self {
+            severity,
+            employee: employee.map(String::from),
+            message,
+        }
+    }
+
+    pub fn warning(employee: Option<&str>, message: String) -> Self {
+        Self::new(Severity::Warning, employee, message)
+    }
+
+    pub fn error(employee: Option<&str>, message: String) -> Self {
+        Self::new(Severity::Error, employee, message)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.employee {
+            Some(id) => write!(f, "employee `{}`: {}", id, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The issues of a timecard that can't be exported because some of them are errors.
+#[derive(Error, Debug)]
+#[error("timecard has {} validation error(s)", .0.iter().filter(|issue| issue.is_error()).count())]
+pub struct ValidationFailed(pub Vec<Issue>);
+
+/// Logs every issue and returns how many of them are errors.
+pub fn report(issues: &[Issue]) -> usize {
+    for issue in issues {
+        match issue.severity {
+            Severity::Warning => warn!("{}", issue),
+            Severity::Error => error!("{}", issue),
+        }
+    }
+
+    issues.iter().filter(|issue| issue.is_error()).count()
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const WORKBOOK_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+/// A folder timecard workbooks are dropped into.
+///
+/// The folder is polled rather than watched for events, as events aren't delivered for
+/// many network shares. A workbook is only ready once its size is the same on two polls
+/// in a row, so files still being copied in are left for later.
+#[derive(Debug, Clone)]
+pub struct Inbox {
+    dir: PathBuf,
+    sizes: HashMap<PathBuf, u64>,
+}
+
+impl Inbox {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            sizes: HashMap::new(),
+        }
+    }
+
+    fn is_workbook(path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        // Excel's lock files for workbooks that are open (`~$Week 1.xlsx`)
+        if name.starts_with("~$") || name.starts_with('.') {
+            return false;
+        }
+
+        path.extension()
+            .map(|ext| {
+                WORKBOOK_EXTENSIONS
+                    .iter()
+                    .any(|known| ext.eq_ignore_ascii_case(known))
+            })
+            .unwrap_or(false)
+    }
+
+    /// The workbooks whose size hasn't changed since the last poll, in name order.
+    pub fn ready(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut sizes = HashMap::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_file() && Self::is_workbook(&path) {
+                sizes.insert(path, metadata.len());
+            }
+        }
+
+        let mut ready: Vec<PathBuf> = sizes
+            .iter()
+            .filter(|(path, size)| self.sizes.get(*path) == Some(size))
+            .map(|(path, _)| path.clone())
+            .collect();
+        ready.sort();
+        self.sizes = sizes;
+
+        Ok(ready)
+    }
+
+    /// Forgets a workbook once it has been moved out, so one dropped in again later under
+    /// the same name waits to settle like any other.
+    pub fn forget(&mut self, path: &Path) {
+        self.sizes.remove(path);
+    }
+}
+
+/// Where a file moved into `dir` goes: under its own name, or with the time added (and a
+/// counter after that) when a file of that name is already there.
+fn free_path(dir: &Path, path: &Path) -> PathBuf {
+    let target = dir.join(path.file_name().unwrap_or_default());
+    if !target.exists() {
+        return target;
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let stamped = format!("{}-{}", stem, chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{}{}", stamped, extension)),
+            n => dir.join(format!("{}-{}{}", stamped, n, extension)),
+        })
+        .find(|target| !target.exists())
+        .expect("a free name")
+}
+
+/// Moves a file into a folder, creating the folder if needed, without replacing a file
+/// already there. Falls back to copying when the folder is on another drive.
+pub fn move_into(path: &Path, dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let target = free_path(dir, path);
+    if fs::rename(path, &target).is_err() {
+        fs::copy(path, &target)?;
+        fs::remove_file(path)?;
+    }
+
+    Ok(target)
+}
+
+/// The path for an output next to others for the same workbook, e.g. `Week 1-sage.xlsx`.
+pub fn output_path(dir: &Path, input: &Path, suffix: &str) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    dir.join(format!("{}{}", stem, suffix))
+}
+
+#[test]
+fn it_waits_for_workbooks_to_settle() {
+    let dir = std::env::temp_dir().join(format!("autotimecard-inbox-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("Week 1.xlsx"), b"one").unwrap();
+    fs::write(dir.join("~$Week 1.xlsx"), b"lock").unwrap();
+    fs::write(dir.join("notes.txt"), b"notes").unwrap();
+
+    let mut inbox = Inbox::new(dir.clone());
+    assert!(inbox.ready().unwrap().is_empty());
+    fs::write(dir.join("Week 2.xlsx"), b"two").unwrap();
+    assert_eq!(inbox.ready().unwrap(), vec![dir.join("Week 1.xlsx")]);
+
+    fs::write(dir.join("Week 2.xlsx"), b"two, still copying").unwrap();
+    assert_eq!(inbox.ready().unwrap(), vec![dir.join("Week 1.xlsx")]);
+
+    let moved = move_into(&dir.join("Week 1.xlsx"), &dir.join("processed")).unwrap();
+    inbox.forget(&dir.join("Week 1.xlsx"));
+    assert!(moved.exists());
+    assert_eq!(inbox.ready().unwrap(), vec![dir.join("Week 2.xlsx")]);
+    assert_eq!(
+        output_path(&dir, &moved, "-sage.xlsx"),
+        dir.join("Week 1-sage.xlsx")
+    );
+
+    // A workbook dropped again under the same name doesn't replace the earlier one.
+    fs::write(dir.join("Week 1.xlsx"), b"one again").unwrap();
+    let again = move_into(&dir.join("Week 1.xlsx"), &dir.join("processed")).unwrap();
+    assert_ne!(again, moved);
+    assert_eq!(fs::read(&moved).unwrap(), b"one");
+    assert_eq!(fs::read(&again).unwrap(), b"one again");
+
+    fs::remove_dir_all(&dir).unwrap();
+}